@@ -0,0 +1,15 @@
+-- V0003__capture_renditions.sql
+-- Multi-size thumbnail renditions generated by the worker
+
+CREATE TABLE IF NOT EXISTS capture_renditions (
+    capture_id UUID NOT NULL REFERENCES captures(id) ON DELETE CASCADE,
+    name VARCHAR(50) NOT NULL,
+    format VARCHAR(10) NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    object_key TEXT NOT NULL,
+    url TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (capture_id, name, format)
+);
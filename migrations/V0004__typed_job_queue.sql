@@ -0,0 +1,32 @@
+-- V0004__typed_job_queue.sql
+-- Generalise analysis_queue into a typed job queue (analyze, thumbnail, exif, hash)
+-- with independent retries per job type
+
+ALTER TABLE analysis_queue RENAME TO job_queue;
+ALTER INDEX IF EXISTS idx_analysis_queue_status RENAME TO idx_job_queue_status;
+ALTER INDEX IF EXISTS idx_analysis_queue_created_at RENAME TO idx_job_queue_created_at;
+
+ALTER TABLE job_queue ADD COLUMN IF NOT EXISTS job_type VARCHAR(50) NOT NULL DEFAULT 'analyze';
+ALTER TABLE job_queue ADD COLUMN IF NOT EXISTS run_after TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE job_queue ADD COLUMN IF NOT EXISTS completed_at TIMESTAMPTZ;
+
+-- Keep only the newest row per (capture_id, job_type) before adding the unique constraint
+DELETE FROM job_queue a
+USING job_queue b
+WHERE a.capture_id = b.capture_id
+  AND a.job_type = b.job_type
+  AND (a.created_at, a.id) < (b.created_at, b.id);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_job_queue_capture_job_type ON job_queue(capture_id, job_type);
+CREATE INDEX IF NOT EXISTS idx_job_queue_pending_run_after ON job_queue(run_after) WHERE status = 'pending';
+
+-- Results of the image processing jobs
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS image_width INTEGER;
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS image_height INTEGER;
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS exif_orientation SMALLINT;
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS content_hash VARCHAR(64);
+CREATE INDEX IF NOT EXISTS idx_captures_content_hash ON captures(content_hash);
+
+-- Existing captures are not queued here: queueing every capture at once would
+-- re-download the whole bucket. The worker backfills them in small batches when
+-- IMAGE_JOB_BACKFILL_ENABLED=true.
@@ -0,0 +1,5 @@
+-- V0005__capture_placeholders.sql
+-- BlurHash and dominant colour palette used by clients as instant placeholders
+
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS blurhash VARCHAR(64);
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS dominant_colors TEXT[];
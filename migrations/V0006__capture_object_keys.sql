@@ -0,0 +1,26 @@
+-- V0006__capture_object_keys.sql
+-- Object keys become the source of truth; URLs are built at read time from the
+-- current storage configuration (endpoint, path style, CDN)
+
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS image_key TEXT;
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS thumbnail_key TEXT;
+
+-- Backfill from existing URLs. Every key issued by the service lives under
+-- captures/ or thumbnails/, whatever the host or addressing style of the URL;
+-- values that are already bare keys are kept as-is.
+UPDATE captures
+SET image_key = CASE
+        WHEN image_url ~ '^(captures|thumbnails)/' THEN split_part(image_url, '?', 1)
+        ELSE substring(split_part(image_url, '?', 1) FROM '/((?:captures|thumbnails)/.+)$')
+    END
+WHERE image_key IS NULL AND image_url IS NOT NULL;
+
+UPDATE captures
+SET thumbnail_key = CASE
+        WHEN thumbnail_url ~ '^(captures|thumbnails)/' THEN split_part(thumbnail_url, '?', 1)
+        ELSE substring(split_part(thumbnail_url, '?', 1) FROM '/((?:captures|thumbnails)/.+)$')
+    END
+WHERE thumbnail_key IS NULL AND thumbnail_url IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_captures_image_key ON captures(image_key);
+CREATE INDEX IF NOT EXISTS idx_captures_thumbnail_key ON captures(thumbnail_key);
@@ -0,0 +1,22 @@
+-- V0007__storage_deletions.sql
+-- Outbox of storage objects to delete. Rows are written in the same transaction
+-- that deletes the capture and processed in the background with retries.
+-- No FK to captures: the capture row is gone by the time the object is deleted.
+
+CREATE TABLE IF NOT EXISTS storage_deletions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    object_key TEXT NOT NULL,
+    capture_id UUID,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    run_after TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+-- At most one pending deletion per object, so re-deleting is idempotent
+CREATE UNIQUE INDEX IF NOT EXISTS idx_storage_deletions_pending_key
+    ON storage_deletions(object_key) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_storage_deletions_run_after
+    ON storage_deletions(run_after) WHERE status = 'pending';
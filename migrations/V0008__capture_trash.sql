@@ -0,0 +1,12 @@
+-- V0008__capture_trash.sql
+-- Soft-deleted captures stay in the trash until restored or purged after the
+-- retention period (TRASH_RETENTION_DAYS)
+
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+-- Rows soft-deleted before this column existed start their retention now
+UPDATE captures SET deleted_at = NOW()
+WHERE is_deleted = true AND deleted_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_captures_trash
+    ON captures(user_id, deleted_at DESC) WHERE is_deleted = true;
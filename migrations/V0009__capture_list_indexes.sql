@@ -0,0 +1,23 @@
+-- V0009__capture_list_indexes.sql
+-- Indexes backing the filters and sort options of GET /captures.
+-- Listings only ever read active captures, hence the partial indexes.
+
+CREATE INDEX IF NOT EXISTS idx_captures_active_created
+    ON captures(created_at DESC, id DESC) WHERE is_deleted = false;
+CREATE INDEX IF NOT EXISTS idx_captures_active_updated
+    ON captures(updated_at DESC, id DESC) WHERE is_deleted = false;
+CREATE INDEX IF NOT EXISTS idx_captures_active_confidence
+    ON captures(confidence DESC NULLS LAST, id DESC) WHERE is_deleted = false;
+
+CREATE INDEX IF NOT EXISTS idx_captures_active_user_created
+    ON captures(user_id, created_at DESC, id DESC) WHERE is_deleted = false;
+CREATE INDEX IF NOT EXISTS idx_captures_active_category_created
+    ON captures(category, created_at DESC) WHERE is_deleted = false;
+CREATE INDEX IF NOT EXISTS idx_captures_active_difficulty_created
+    ON captures(difficulty, created_at DESC) WHERE is_deleted = false;
+CREATE INDEX IF NOT EXISTS idx_captures_active_public_created
+    ON captures(is_public, created_at DESC) WHERE is_deleted = false;
+
+-- tags && / @> (any / all)
+CREATE INDEX IF NOT EXISTS idx_captures_tags_gin
+    ON captures USING GIN (tags) WHERE is_deleted = false;
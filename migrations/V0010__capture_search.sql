@@ -0,0 +1,41 @@
+-- V0010__capture_search.sql
+-- Full-text search over the analysis name, description, broader context and tags.
+-- Tags are Spanish and stored without accents, so documents and queries go through
+-- an accent-insensitive Spanish configuration ("volcan" matches "Volcán").
+
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = 'spanish_unaccent') THEN
+        CREATE TEXT SEARCH CONFIGURATION spanish_unaccent (COPY = spanish);
+        ALTER TEXT SEARCH CONFIGURATION spanish_unaccent
+            ALTER MAPPING FOR hword, hword_part, word WITH unaccent, spanish_stem;
+    END IF;
+END
+$$;
+
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS search_vector TSVECTOR;
+
+-- Weights: name A, tags B, description C, broader context D
+CREATE OR REPLACE FUNCTION captures_search_vector_update() RETURNS trigger AS $$
+BEGIN
+    NEW.search_vector :=
+        setweight(to_tsvector('spanish_unaccent', COALESCE(NEW.vision_result->>'name', '')), 'A') ||
+        setweight(to_tsvector('spanish_unaccent', COALESCE(array_to_string(NEW.tags, ' '), '')), 'B') ||
+        setweight(to_tsvector('spanish_unaccent', COALESCE(NEW.vision_result->>'description', '')), 'C') ||
+        setweight(to_tsvector('spanish_unaccent', COALESCE(NEW.vision_result->>'broader_context', '')), 'D');
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_captures_search_vector ON captures;
+CREATE TRIGGER trg_captures_search_vector
+    BEFORE INSERT OR UPDATE OF vision_result, tags ON captures
+    FOR EACH ROW EXECUTE FUNCTION captures_search_vector_update();
+
+-- Backfill: the no-op update fires the trigger
+UPDATE captures SET vision_result = vision_result WHERE search_vector IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_captures_search_vector
+    ON captures USING GIN (search_vector) WHERE is_deleted = false;
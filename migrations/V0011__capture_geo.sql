@@ -0,0 +1,53 @@
+-- V0011__capture_geo.sql
+-- Latitude/longitude promoted from the free-form `location` JSON to indexed columns
+-- for the map queries (nearby and bounding box). Uses cube + earthdistance, which
+-- ship with Postgres, rather than requiring PostGIS.
+
+CREATE EXTENSION IF NOT EXISTS cube;
+CREATE EXTENSION IF NOT EXISTS earthdistance;
+
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS latitude DOUBLE PRECISION;
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS longitude DOUBLE PRECISION;
+
+-- Kept in sync with `location` ({"latitude": .., "longitude": ..}); missing,
+-- non-numeric or out-of-range coordinates leave both columns NULL
+CREATE OR REPLACE FUNCTION captures_location_update() RETURNS trigger AS $$
+DECLARE
+    loc JSONB := NEW.location::JSONB;
+    lat DOUBLE PRECISION;
+    lon DOUBLE PRECISION;
+BEGIN
+    IF jsonb_typeof(loc->'latitude') = 'number'
+       AND jsonb_typeof(loc->'longitude') = 'number' THEN
+        lat := (loc->>'latitude')::DOUBLE PRECISION;
+        lon := (loc->>'longitude')::DOUBLE PRECISION;
+    END IF;
+
+    IF lat BETWEEN -90 AND 90 AND lon BETWEEN -180 AND 180 THEN
+        NEW.latitude := lat;
+        NEW.longitude := lon;
+    ELSE
+        NEW.latitude := NULL;
+        NEW.longitude := NULL;
+    END IF;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_captures_location ON captures;
+CREATE TRIGGER trg_captures_location
+    BEFORE INSERT OR UPDATE OF location ON captures
+    FOR EACH ROW EXECUTE FUNCTION captures_location_update();
+
+-- Backfill: the no-op update fires the trigger
+UPDATE captures SET location = location WHERE location IS NOT NULL;
+
+-- Radius searches (earth_box @> ll_to_earth)
+CREATE INDEX IF NOT EXISTS idx_captures_earth
+    ON captures USING GIST (ll_to_earth(latitude, longitude))
+    WHERE is_deleted = false AND latitude IS NOT NULL;
+
+-- Bounding box searches
+CREATE INDEX IF NOT EXISTS idx_captures_lat_lon
+    ON captures(latitude, longitude)
+    WHERE is_deleted = false AND latitude IS NOT NULL;
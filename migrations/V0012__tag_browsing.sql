@@ -0,0 +1,13 @@
+-- V0012__tag_browsing.sql
+-- Support for the tag browsing API: when each tag was attached (trending) and
+-- prefix lookups on tag names (autocomplete)
+
+ALTER TABLE capture_tags ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+-- Existing links date from their capture
+UPDATE capture_tags ct SET created_at = c.created_at
+FROM captures c
+WHERE c.id = ct.capture_id;
+
+CREATE INDEX IF NOT EXISTS idx_capture_tags_created_at ON capture_tags(created_at);
+CREATE INDEX IF NOT EXISTS idx_tags_name_pattern ON tags(name text_pattern_ops);
@@ -0,0 +1,50 @@
+-- V0013__capture_notes.sql
+-- Personal notes on captures, searchable by their owner, with a revision history of
+-- every edit.
+
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS notes TEXT;
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'captures_notes_length') THEN
+        ALTER TABLE captures
+            ADD CONSTRAINT captures_notes_length CHECK (char_length(notes) <= 5000);
+    END IF;
+END
+$$;
+
+-- One row per saved version of the notes (NULL when they were cleared)
+CREATE TABLE IF NOT EXISTS capture_note_revisions (
+    id BIGSERIAL PRIMARY KEY,
+    capture_id UUID NOT NULL REFERENCES captures(id) ON DELETE CASCADE,
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_capture_note_revisions_capture
+    ON capture_note_revisions (capture_id, created_at DESC, id DESC);
+
+-- Notes are private to the owner of a capture: they stay out of search_vector, which
+-- every search matches against, in their own notes_vector that searches only use for
+-- the requester's own captures
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS notes_vector TSVECTOR;
+
+CREATE OR REPLACE FUNCTION captures_search_vector_update() RETURNS trigger AS $$
+BEGIN
+    NEW.search_vector :=
+        setweight(to_tsvector('spanish_unaccent', COALESCE(NEW.vision_result->>'name', '')), 'A') ||
+        setweight(to_tsvector('spanish_unaccent', COALESCE(array_to_string(NEW.tags, ' '), '')), 'B') ||
+        setweight(to_tsvector('spanish_unaccent', COALESCE(NEW.vision_result->>'description', '')), 'C') ||
+        setweight(to_tsvector('spanish_unaccent', COALESCE(NEW.vision_result->>'broader_context', '')), 'D');
+    NEW.notes_vector := to_tsvector('spanish_unaccent', NEW.notes);
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_captures_search_vector ON captures;
+CREATE TRIGGER trg_captures_search_vector
+    BEFORE INSERT OR UPDATE OF vision_result, tags, notes ON captures
+    FOR EACH ROW EXECUTE FUNCTION captures_search_vector_update();
+
+CREATE INDEX IF NOT EXISTS idx_captures_notes_vector
+    ON captures USING GIN (notes_vector) WHERE is_deleted = false;
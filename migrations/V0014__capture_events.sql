@@ -0,0 +1,27 @@
+-- V0014__capture_events.sql
+-- Append-only audit log of capture mutations. No foreign key to captures, so the
+-- history outlives hard deletes.
+
+CREATE TABLE IF NOT EXISTS capture_events (
+    id BIGSERIAL PRIMARY KEY,
+    capture_id UUID NOT NULL,
+    actor TEXT NOT NULL,
+    action TEXT NOT NULL,
+    -- {"field": {"before": ..., "after": ...}} for every changed field
+    changes JSONB NOT NULL DEFAULT '{}'::jsonb,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_capture_events_capture
+    ON capture_events (capture_id, created_at DESC, id DESC);
+
+CREATE OR REPLACE FUNCTION capture_events_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'capture_events is append-only';
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_capture_events_append_only ON capture_events;
+CREATE TRIGGER trg_capture_events_append_only
+    BEFORE UPDATE OR DELETE ON capture_events
+    FOR EACH ROW EXECUTE FUNCTION capture_events_append_only();
@@ -0,0 +1,28 @@
+-- V0015__capture_versioning.sql
+-- Optimistic concurrency for captures: a version (exposed as the ETag) and the fields
+-- the user edited by hand, which the analysis worker must not overwrite.
+
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE captures ADD COLUMN IF NOT EXISTS user_edited_fields TEXT[] NOT NULL DEFAULT '{}';
+
+-- The version only changes with a user-editable or visibility column: worker writes
+-- (thumbnails, EXIF, placeholders, hashes) must not invalidate the ETag a client holds
+CREATE OR REPLACE FUNCTION captures_version_bump() RETURNS trigger AS $$
+BEGIN
+    IF (NEW.tags, NEW.category, NEW.notes, NEW.author_name, NEW.location_info,
+        NEW.difficulty, NEW.is_public, NEW.is_deleted)
+       IS DISTINCT FROM
+       (OLD.tags, OLD.category, OLD.notes, OLD.author_name, OLD.location_info,
+        OLD.difficulty, OLD.is_public, OLD.is_deleted) THEN
+        NEW.version := OLD.version + 1;
+    ELSE
+        NEW.version := OLD.version;
+    END IF;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_captures_version_bump ON captures;
+CREATE TRIGGER trg_captures_version_bump
+    BEFORE UPDATE ON captures
+    FOR EACH ROW EXECUTE FUNCTION captures_version_bump();
@@ -12,6 +12,7 @@ pub struct AppConfig {
     pub logging: LoggingConfig,
     pub worker: WorkerConfig,
     pub webhooks: WebhookConfig,
+    pub tags: TagsConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,12 +39,45 @@ pub struct DatabaseConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageConfig {
+    pub backend: StorageBackend,
     pub aws_region: String,
     pub aws_access_key_id: String,
     pub aws_secret_access_key: String,
     pub s3_bucket: String,
     pub s3_endpoint: Option<String>,
+    pub s3_force_path_style: bool,
+    /// Base pública (CDN) desde la que se sirven los objetos, si existe
+    pub public_base_url: Option<String>,
     pub max_image_size_bytes: usize,
+    /// Ancho/alto máximo de una imagen subida, comprobado en la cabecera antes de decodificar
+    pub max_image_dimension: u32,
+    /// Memoria máxima que puede reservar la decodificación de una imagen subida
+    pub max_image_decode_bytes: u64,
+    pub allowed_content_types: Vec<String>,
+    /// Último día en que se aceptan keys antiguas `captures/{timestamp}/...`, emitidas
+    /// antes de los prefijos por usuario; sin fecha se aceptan siempre
+    pub legacy_upload_keys_until: Option<chrono::NaiveDate>,
+    pub presigned_url_expiry_seconds: u64,
+    /// Firmar las URLs de lectura devueltas por la API (bucket privado)
+    pub signed_read_urls: bool,
+    pub read_url_ttl_seconds: u64,
+    /// Capturas públicas usan URLs sin firmar (CDN / PUBLIC_BASE_URL)
+    pub public_captures_use_cdn: bool,
+    pub max_multipart_upload_bytes: u64,
+    pub multipart_part_size_bytes: u64,
+    pub multipart_upload_ttl_hours: i64,
+    pub local_storage_path: String,
+    pub local_storage_base_url: String,
+    pub storage_signing_secret: String,
+}
+
+/// Backend de almacenamiento de objetos
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    S3,
+    /// Sistema de archivos local, para desarrollo sin S3/MinIO
+    Local,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +93,8 @@ pub struct SecurityConfig {
     pub rate_limit_requests: u32,
     pub rate_limit_window_seconds: u64,
     pub max_request_size_bytes: usize,
+    /// Bearer token for the admin endpoints; they are disabled when unset
+    pub admin_api_token: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -74,6 +110,104 @@ pub struct WorkerConfig {
     pub thumbnail_enabled: bool,
     pub max_thumbnail_width: u32,
     pub max_thumbnail_height: u32,
+    pub thumbnail_renditions: Vec<RenditionSpec>,
+    pub thumbnail_formats: Vec<RenditionFormat>,
+    pub multipart_cleanup_interval_seconds: u64,
+    pub gc_enabled: bool,
+    pub gc_interval_seconds: u64,
+    pub gc_grace_period_hours: i64,
+    pub gc_dry_run: bool,
+    pub storage_deletion_interval_seconds: u64,
+    pub trash_retention_days: i32,
+    pub trash_purge_interval_seconds: u64,
+    /// Encolar poco a poco los jobs de imagen de capturas anteriores a la cola tipada
+    pub image_backfill_enabled: bool,
+    /// Capturas encoladas por tick del worker como máximo durante el backfill
+    pub image_backfill_batch_size: i32,
+}
+
+/// Tamaño de rendition derivada de la imagen original
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenditionSpec {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub mode: ResizeMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeMode {
+    /// Recorta al tamaño exacto (cover)
+    Fill,
+    /// Encaja dentro del tamaño manteniendo proporción, sin ampliar
+    Fit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenditionFormat {
+    Jpeg,
+    Webp,
+}
+
+impl RenditionFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RenditionFormat::Jpeg => "jpeg",
+            RenditionFormat::Webp => "webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RenditionFormat::Jpeg => "jpg",
+            RenditionFormat::Webp => "webp",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            RenditionFormat::Jpeg => "image/jpeg",
+            RenditionFormat::Webp => "image/webp",
+        }
+    }
+}
+
+impl RenditionSpec {
+    /// Parse a comma separated list like `square:200x200:fill,feed:640x640:fit`
+    pub fn parse_list(value: &str) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
+        let mut specs: Vec<Self> = Vec::new();
+        for entry in value.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let parts: Vec<&str> = entry.split(':').collect();
+            if parts.len() < 2 || parts.len() > 3 {
+                return Err(format!("Invalid rendition spec '{}'", entry).into());
+            }
+            let (width, height) = parts[1]
+                .split_once('x')
+                .ok_or_else(|| format!("Invalid rendition size '{}'", parts[1]))?;
+            let mode = match parts.get(2).copied().unwrap_or("fit") {
+                "fill" => ResizeMode::Fill,
+                "fit" => ResizeMode::Fit,
+                other => return Err(format!("Invalid rendition mode '{}'", other).into()),
+            };
+            let name = parts[0].trim();
+            if name.is_empty() {
+                return Err(format!("Invalid rendition spec '{}', empty name", entry).into());
+            }
+            // The name is part of the object key and of the API response
+            if specs.iter().any(|spec| spec.name == name) {
+                return Err(format!("Duplicate rendition name '{}'", name).into());
+            }
+            specs.push(Self {
+                name: name.to_string(),
+                width: width.parse::<u32>()?,
+                height: height.parse::<u32>()?,
+                mode,
+            });
+        }
+        Ok(specs)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,6 +216,13 @@ pub struct WebhookConfig {
     pub enabled: bool,
 }
 
+/// Normalización de tags: sinónimos y traducciones `origen=destino`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagsConfig {
+    pub synonyms: String,
+    pub synonyms_file: Option<String>,
+}
+
 impl AppConfig {
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
         let host = env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
@@ -124,14 +265,82 @@ impl AppConfig {
             .unwrap_or_else(|_| "3600".to_string())
             .parse::<u64>()?;
 
+        let storage_backend = match env::var("STORAGE_BACKEND")
+            .unwrap_or_else(|_| "s3".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "s3" => StorageBackend::S3,
+            "local" => StorageBackend::Local,
+            other => return Err(format!("Invalid STORAGE_BACKEND '{}'", other).into()),
+        };
+        // AWS credentials are only required for the S3 backend
+        let s3_var = |name: &str| match storage_backend {
+            StorageBackend::S3 => env::var(name),
+            StorageBackend::Local => Ok(env::var(name).unwrap_or_default()),
+        };
         let aws_region = env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string());
-        let aws_access_key_id = env::var("AWS_ACCESS_KEY_ID")?;
-        let aws_secret_access_key = env::var("AWS_SECRET_ACCESS_KEY")?;
-        let s3_bucket = env::var("S3_BUCKET")?;
+        let aws_access_key_id = s3_var("AWS_ACCESS_KEY_ID")?;
+        let aws_secret_access_key = s3_var("AWS_SECRET_ACCESS_KEY")?;
+        let s3_bucket = s3_var("S3_BUCKET")?;
         let s3_endpoint = env::var("S3_ENDPOINT").ok();
+        // MinIO/LocalStack normally need path style; AWS defaults to virtual hosted
+        let s3_force_path_style = env::var("S3_FORCE_PATH_STYLE")
+            .map(|v| v.to_lowercase() == "true")
+            .unwrap_or(s3_endpoint.is_some());
+        let public_base_url = env::var("PUBLIC_BASE_URL").ok().filter(|s| !s.is_empty());
         let max_image_size_bytes = env::var("MAX_IMAGE_SIZE_BYTES")
             .unwrap_or_else(|_| "10485760".to_string())
             .parse::<usize>()?;
+        let max_image_dimension = env::var("MAX_IMAGE_DIMENSION")
+            .unwrap_or_else(|_| "8192".to_string())
+            .parse::<u32>()?;
+        let max_image_decode_bytes = env::var("MAX_IMAGE_DECODE_BYTES")
+            .unwrap_or_else(|_| "268435456".to_string())
+            .parse::<u64>()?;
+        let allowed_content_types = env::var("ALLOWED_IMAGE_CONTENT_TYPES")
+            .unwrap_or_else(|_| "image/jpeg,image/png,image/webp".to_string())
+            .split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect();
+        let legacy_upload_keys_until = env::var("LEGACY_UPLOAD_KEYS_UNTIL")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(|s| chrono::NaiveDate::parse_from_str(&s, "%Y-%m-%d"))
+            .transpose()?;
+        let presigned_url_expiry_seconds = env::var("PRESIGNED_URL_EXPIRY_SECONDS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()?;
+        let signed_read_urls = env::var("SIGNED_READ_URLS")
+            .unwrap_or_else(|_| "true".to_string())
+            .to_lowercase()
+            == "true";
+        let read_url_ttl_seconds = env::var("READ_URL_TTL_SECONDS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()?;
+        let public_captures_use_cdn = env::var("PUBLIC_CAPTURES_USE_CDN")
+            .unwrap_or_else(|_| "false".to_string())
+            .to_lowercase()
+            == "true";
+        let max_multipart_upload_bytes = env::var("MAX_MULTIPART_UPLOAD_BYTES")
+            .unwrap_or_else(|_| "524288000".to_string())
+            .parse::<u64>()?;
+        let multipart_part_size_bytes = env::var("MULTIPART_PART_SIZE_BYTES")
+            .unwrap_or_else(|_| "8388608".to_string())
+            .parse::<u64>()?;
+        // S3 rejects parts smaller than 5 MiB (except the last one)
+        if multipart_part_size_bytes < 5 * 1024 * 1024 {
+            return Err("MULTIPART_PART_SIZE_BYTES must be at least 5242880".into());
+        }
+        let multipart_upload_ttl_hours = env::var("MULTIPART_UPLOAD_TTL_HOURS")
+            .unwrap_or_else(|_| "24".to_string())
+            .parse::<i64>()?;
+        let local_storage_path =
+            env::var("LOCAL_STORAGE_PATH").unwrap_or_else(|_| "./data/uploads".to_string());
+        let local_storage_base_url = env::var("LOCAL_STORAGE_BASE_URL")
+            .unwrap_or_else(|_| format!("http://{}:{}/api/v1/files", host, port));
+        let storage_signing_secret = env::var("STORAGE_SIGNING_SECRET").unwrap_or_default();
 
         let gemini_api_key = env::var("GEMINI_API_KEY")?;
         let gemini_endpoint = env::var("GEMINI_ENDPOINT")
@@ -153,6 +362,9 @@ impl AppConfig {
         let max_request_size_bytes = env::var("MAX_REQUEST_SIZE_BYTES")
             .unwrap_or_else(|_| "52428800".to_string())
             .parse::<usize>()?;
+        let admin_api_token = env::var("ADMIN_API_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty());
 
         let logging_level = env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string());
         let logging_format = env::var("LOG_FORMAT").unwrap_or_else(|_| "json".to_string());
@@ -169,11 +381,72 @@ impl AppConfig {
             .to_lowercase()
             == "true";
         let max_thumbnail_width = env::var("MAX_THUMBNAIL_WIDTH")
-            .unwrap_or_else(|_| "400".to_string())
+            .unwrap_or_else(|_| "1280".to_string())
             .parse::<u32>()?;
         let max_thumbnail_height = env::var("MAX_THUMBNAIL_HEIGHT")
-            .unwrap_or_else(|_| "400".to_string())
+            .unwrap_or_else(|_| "1280".to_string())
             .parse::<u32>()?;
+        let thumbnail_renditions = RenditionSpec::parse_list(
+            &env::var("THUMBNAIL_RENDITIONS").unwrap_or_else(|_| {
+                "square:200x200:fill,feed:640x640:fit,detail:1280x1280:fit".to_string()
+            }),
+        )?
+        .into_iter()
+        .map(|mut spec| {
+            // MAX_THUMBNAIL_* acts as an upper bound for every rendition. The default went
+            // from 400 (unused by the old single 200x200 thumbnail) to 1280 so it does not
+            // shrink the default `detail` rendition.
+            spec.width = spec.width.min(max_thumbnail_width);
+            spec.height = spec.height.min(max_thumbnail_height);
+            spec
+        })
+        .collect();
+        // WebP is opt-in: the encoder in `image` is lossless-only, so WebP renditions of
+        // photos come out larger than the JPEG ones
+        let thumbnail_formats = env::var("THUMBNAIL_FORMATS")
+            .unwrap_or_else(|_| "jpeg".to_string())
+            .split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .map(|s| match s.as_str() {
+                "jpeg" | "jpg" => Ok(RenditionFormat::Jpeg),
+                "webp" => Ok(RenditionFormat::Webp),
+                other => Err(format!("Invalid thumbnail format '{}'", other)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let multipart_cleanup_interval_seconds = env::var("MULTIPART_CLEANUP_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()?;
+        let gc_enabled = env::var("GC_ENABLED")
+            .unwrap_or_else(|_| "false".to_string())
+            .to_lowercase()
+            == "true";
+        let gc_interval_seconds = env::var("GC_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "86400".to_string())
+            .parse::<u64>()?;
+        let gc_grace_period_hours = env::var("GC_GRACE_PERIOD_HOURS")
+            .unwrap_or_else(|_| "24".to_string())
+            .parse::<i64>()?;
+        let gc_dry_run = env::var("GC_DRY_RUN")
+            .unwrap_or_else(|_| "false".to_string())
+            .to_lowercase()
+            == "true";
+        let storage_deletion_interval_seconds = env::var("STORAGE_DELETION_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse::<u64>()?;
+        let trash_retention_days = env::var("TRASH_RETENTION_DAYS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse::<i32>()?;
+        let trash_purge_interval_seconds = env::var("TRASH_PURGE_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()?;
+        let image_backfill_enabled = env::var("IMAGE_JOB_BACKFILL_ENABLED")
+            .unwrap_or_else(|_| "false".to_string())
+            .to_lowercase()
+            == "true";
+        let image_backfill_batch_size = env::var("IMAGE_JOB_BACKFILL_BATCH_SIZE")
+            .unwrap_or_else(|_| "20".to_string())
+            .parse::<i32>()?;
 
         let stories_service_url =
             env::var("STORIES_SERVICE_URL").unwrap_or_else(|_| "http://localhost:8083".to_string());
@@ -202,12 +475,29 @@ impl AppConfig {
                 max_lifetime_seconds: db_max_lifetime,
             },
             storage: StorageConfig {
+                backend: storage_backend,
                 aws_region,
                 aws_access_key_id,
                 aws_secret_access_key,
                 s3_bucket,
                 s3_endpoint,
+                s3_force_path_style,
+                public_base_url,
                 max_image_size_bytes,
+                max_image_dimension,
+                max_image_decode_bytes,
+                allowed_content_types,
+                legacy_upload_keys_until,
+                presigned_url_expiry_seconds,
+                signed_read_urls,
+                read_url_ttl_seconds,
+                public_captures_use_cdn,
+                max_multipart_upload_bytes,
+                multipart_part_size_bytes,
+                multipart_upload_ttl_hours,
+                local_storage_path,
+                local_storage_base_url,
+                storage_signing_secret,
             },
             ai: AIConfig {
                 gemini_api_key,
@@ -219,6 +509,7 @@ impl AppConfig {
                 rate_limit_requests,
                 rate_limit_window_seconds,
                 max_request_size_bytes,
+                admin_api_token,
             },
             logging: LoggingConfig {
                 level: logging_level,
@@ -230,11 +521,59 @@ impl AppConfig {
                 thumbnail_enabled,
                 max_thumbnail_width,
                 max_thumbnail_height,
+                thumbnail_renditions,
+                thumbnail_formats,
+                multipart_cleanup_interval_seconds,
+                gc_enabled,
+                gc_interval_seconds,
+                gc_grace_period_hours,
+                gc_dry_run,
+                storage_deletion_interval_seconds,
+                trash_retention_days,
+                trash_purge_interval_seconds,
+                image_backfill_enabled,
+                image_backfill_batch_size,
             },
             webhooks: WebhookConfig {
                 stories_service_url,
                 enabled: webhooks_enabled,
             },
+            tags: TagsConfig {
+                synonyms: env::var("TAG_SYNONYMS").unwrap_or_default(),
+                synonyms_file: env::var("TAG_SYNONYMS_FILE")
+                    .ok()
+                    .filter(|path| !path.is_empty()),
+            },
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rendition_list() {
+        let specs = RenditionSpec::parse_list("square:200x200:fill, feed:640x480").unwrap();
+
+        assert_eq!(specs.len(), 2);
+        assert_eq!(specs[0].name, "square");
+        assert_eq!((specs[0].width, specs[0].height), (200, 200));
+        assert_eq!(specs[0].mode, ResizeMode::Fill);
+        assert_eq!((specs[1].width, specs[1].height), (640, 480));
+        assert_eq!(specs[1].mode, ResizeMode::Fit);
+    }
+
+    #[test]
+    fn rejects_duplicate_and_malformed_renditions() {
+        let duplicate = RenditionSpec::parse_list("feed:640x640:fit,feed:320x320:fill");
+        assert_eq!(
+            duplicate.unwrap_err().to_string(),
+            "Duplicate rendition name 'feed'"
+        );
+
+        assert!(RenditionSpec::parse_list(":200x200").is_err());
+        assert!(RenditionSpec::parse_list("square:200").is_err());
+        assert!(RenditionSpec::parse_list("square:200x200:crop").is_err());
+    }
+}
@@ -1,13 +1,63 @@
 use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 use deadpool_postgres::{Config, ManagerConfig, Pool, RecyclingMethod, Runtime};
+use tokio_postgres::types::ToSql;
 use tokio_postgres::NoTls;
 use uuid::Uuid;
 
 use crate::config::DatabaseConfig;
-use crate::models::{AnalysisResult, Capture, DeviceUpload};
+use crate::models::{
+    AnalysisResult, BboxQuery, Capture, CaptureAction, CaptureCluster, CaptureCursor,
+    CaptureEvent, CaptureListQuery, CaptureRendition, CaptureSort, DeviceUpload, Job, JobType,
+    SortOrder, StorageDeletion, TagCount, TagMatch, TrendingTag, ACTOR_TRASH_PURGE, ACTOR_WORKER,
+    apply_merge_patch,
+};
 
 pub type DbPool = Pool;
 
+/// Parameter of a query built at runtime
+type SqlParam = Box<dyn ToSql + Sync + Send>;
+
+/// Columns selected for a `Capture`, in the order expected by `row_to_capture`
+const CAPTURE_COLUMNS: &str = "id, user_id, author_name, device_local_id, image_url, thumbnail_url, image_size, storage_type,
+    vision_result, category, confidence, tags, location, location_info, orientation,
+    is_deleted, created_at, updated_at, difficulty, verified, is_public,
+    image_width, image_height, exif_orientation, content_hash, blurhash, dominant_colors,
+    image_key, thumbnail_key, deleted_at, latitude, longitude, notes, version, user_edited_fields";
+
+/// Capture fields left out of event diffs: derived from other columns or rebuilt on read
+const UNAUDITED_FIELDS: &[&str] = &[
+    "updated_at",
+    "version",
+    "image_url",
+    "thumbnail_url",
+    "latitude",
+    "longitude",
+    "renditions",
+    "urls_expire_at",
+];
+
+/// Private capture fields whose values are masked in event diffs: the history is
+/// readable by anyone who can see the capture
+const REDACTED_FIELDS: &[&str] = &["notes", "location", "location_info"];
+
+/// Outcome of an update guarded by an expected version (`If-Match`)
+pub enum CaptureUpdate {
+    Updated(Box<Capture>),
+    NotFound,
+    VersionMismatch { current_version: i32 },
+}
+
+impl CaptureUpdate {
+    /// The updated capture, for callers that do not check versions
+    pub fn updated(self) -> Option<Capture> {
+        match self {
+            CaptureUpdate::Updated(capture) => Some(*capture),
+            _ => None,
+        }
+    }
+}
+
 pub struct DatabaseService {
     pool: DbPool,
 }
@@ -48,21 +98,21 @@ impl DatabaseService {
     pub async fn create_capture(
         &self,
         req: &crate::models::CreateCaptureRequest,
+        actor: &str,
     ) -> Result<Capture, Box<dyn std::error::Error + Send + Sync>> {
-        let client = self.get_client().await?;
+        let mut client = self.get_client().await?;
+        let tx = client.transaction().await?;
 
         let id = Uuid::new_v4();
         let now = Utc::now();
 
-        let row = client.query_one("
+        let row = tx.query_one(&format!("
             INSERT INTO captures (id, user_id, author_name, device_local_id, image_url, thumbnail_url, image_size, 
                                 vision_result, category, confidence, tags, location, location_info, 
-                                orientation, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
-            RETURNING id, user_id, author_name, device_local_id, image_url, thumbnail_url, image_size, storage_type,
-                      vision_result, category, confidence, tags, location, location_info, orientation,
-                      is_deleted, created_at, updated_at, difficulty, verified, is_public
-        ", &[
+                                orientation, created_at, updated_at, storage_type, image_key, thumbnail_key)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, COALESCE($17, 's3'), $18, $19)
+            RETURNING {CAPTURE_COLUMNS}
+        "), &[
             &id,
             &req.user_id,
             &req.author_name,
@@ -78,10 +128,18 @@ impl DatabaseService {
             &req.location_info,
             &req.orientation,
             &now,
-            &now
+            &now,
+            &req.storage_type,
+            &req.image_key,
+            &req.thumbnail_key
         ]).await?;
+        let capture = Self::row_to_capture(&row);
 
-        Ok(Self::row_to_capture(&row))
+        let changes = Self::capture_changes(None, Some(&capture));
+        Self::record_capture_event(&tx, &id, actor, CaptureAction::Created, &changes).await?;
+
+        tx.commit().await?;
+        Ok(capture)
     }
 
     /// Get capture by ID
@@ -93,12 +151,12 @@ impl DatabaseService {
 
         let rows = client
             .query(
-                "
-                 SELECT id, user_id, author_name, device_local_id, image_url, thumbnail_url, image_size, storage_type,
-                     vision_result, category, confidence, tags, location, location_info, orientation,
-                     is_deleted, created_at, updated_at, difficulty, verified, is_public
+                &format!(
+                    "
+            SELECT {CAPTURE_COLUMNS}
             FROM captures WHERE id = $1 AND is_deleted = false
-        ",
+        "
+                ),
                 &[id],
             )
             .await?;
@@ -110,385 +168,1435 @@ impl DatabaseService {
         Ok(Some(Self::row_to_capture(&rows[0])))
     }
 
-    /// Get captures with pagination
+    /// Get captures matching `query` in the requested order. With `cursor` the page
+    /// starts right after it (keyset) and `offset` is ignored. The total is only
+    /// counted when `include_total` is set.
     pub async fn get_captures(
         &self,
-        user_id: Option<Uuid>,
-        page: i32,
+        query: &CaptureListQuery,
+        cursor: Option<&CaptureCursor>,
+        offset: i32,
         limit: i32,
-    ) -> Result<(Vec<Capture>, i64), Box<dyn std::error::Error + Send + Sync>> {
+        include_total: bool,
+    ) -> Result<(Vec<Capture>, Option<i64>), Box<dyn std::error::Error + Send + Sync>> {
         let client = self.get_client().await?;
-        let offset = (page - 1) * limit;
-
-        let count_query = if user_id.is_some() {
-            "SELECT COUNT(*) FROM captures WHERE user_id = $1 AND is_deleted = false"
-        } else {
-            "SELECT COUNT(*) FROM captures WHERE is_deleted = false"
-        };
 
-        let total: i64 = if let Some(uid) = user_id {
-            let row = client.query_one(count_query, &[&uid]).await?;
-            row.get(0)
-        } else {
-            let row = client.query_one(count_query, &[]).await?;
-            row.get(0)
-        };
+        let (mut where_clause, mut params) = Self::capture_filter(query);
 
-        let query = if user_id.is_some() {
-            "SELECT id, user_id, author_name, device_local_id, image_url, thumbnail_url, image_size, storage_type,
-                    vision_result, category, confidence, tags, location, location_info, orientation,
-                    is_deleted, created_at, updated_at, difficulty, verified, is_public
-                 FROM captures WHERE user_id = $1 AND is_deleted = false
-             ORDER BY created_at DESC LIMIT $2 OFFSET $3"
+        let total = if include_total {
+            let count_query = format!("SELECT COUNT(*) FROM captures WHERE {where_clause}");
+            let row = client
+                .query_one(&count_query, &Self::param_refs(&params))
+                .await?;
+            Some(row.get(0))
         } else {
-            "SELECT id, user_id, author_name, device_local_id, image_url, thumbnail_url, image_size, storage_type,
-                    vision_result, category, confidence, tags, location, location_info, orientation,
-                    is_deleted, created_at, updated_at, difficulty, verified, is_public
-             FROM captures WHERE is_deleted = false
-             ORDER BY created_at DESC LIMIT $1 OFFSET $2"
+            None
         };
 
-        let rows = if let Some(uid) = user_id {
-            client.query(query, &[&uid, &limit, &offset]).await?
-        } else {
-            client.query(query, &[&limit, &offset]).await?
+        let offset = match cursor {
+            Some(cursor) => {
+                where_clause.push_str(" AND ");
+                where_clause.push_str(&Self::keyset_condition(cursor, &mut params));
+                0
+            }
+            None => offset,
         };
 
-        let captures = rows.iter().map(|row| Self::row_to_capture(row)).collect();
-
-        Ok((captures, total))
-    }
+        let limit_idx = params.len() + 1;
+        let list_query = format!(
+            "SELECT {CAPTURE_COLUMNS}
+             FROM captures WHERE {where_clause}
+             ORDER BY {order_by}
+             LIMIT ${limit_idx} OFFSET ${offset_idx}",
+            order_by = Self::capture_order(query),
+            offset_idx = limit_idx + 1,
+        );
+        params.push(Box::new(limit as i64));
+        params.push(Box::new(offset as i64));
 
-    /// Update capture
-    pub async fn update_capture(
-        &self,
-        id: &Uuid,
-        req: &crate::models::UpdateCaptureRequest,
-    ) -> Result<Option<Capture>, Box<dyn std::error::Error + Send + Sync>> {
-        let client = self.get_client().await?;
-        let now = Utc::now();
+        let rows = client
+            .query(&list_query, &Self::param_refs(&params))
+            .await?;
 
-        let row = client.query_opt("
-            UPDATE captures SET
-                tags = COALESCE($2, tags),
-                category = COALESCE($3, category),
-                updated_at = $4
-            WHERE id = $1 AND is_deleted = false
-            RETURNING id, user_id, author_name, device_local_id, image_url, thumbnail_url, image_size, storage_type,
-                      vision_result, category, confidence, tags, location, location_info, orientation,
-                      is_deleted, created_at, updated_at, difficulty, verified, is_public
-        ", &[id, &req.tags, &req.category, &now]).await?;
+        let captures = rows.iter().map(Self::row_to_capture).collect();
 
-        Ok(row.map(|r| Self::row_to_capture(&r)))
+        Ok((captures, total))
     }
 
-    /// Soft delete capture
-    pub async fn delete_capture(
+    /// Full-text search over active captures, best matches first. Returns each
+    /// capture with its rank and a highlighted headline. Notes are private, so they
+    /// are only searched (and shown in the headline) on captures owned by `viewer`.
+    pub async fn search_captures(
         &self,
-        id: &Uuid,
-    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        text: &str,
+        user_id: Option<Uuid>,
+        viewer: Option<Uuid>,
+        offset: i32,
+        limit: i32,
+    ) -> Result<Vec<(Capture, f32, String)>, Box<dyn std::error::Error + Send + Sync>> {
         let client = self.get_client().await?;
+        let limit_i64 = limit as i64;
+        let offset_i64 = offset as i64;
 
-        let result = client
-            .execute(
-                "
-            UPDATE captures SET is_deleted = true, updated_at = NOW()
-            WHERE id = $1 AND is_deleted = false
-        ",
-                &[id],
+        let rows = client
+            .query(
+                &format!(
+                    "
+            SELECT {CAPTURE_COLUMNS},
+                ts_rank_cd(
+                    CASE WHEN owned
+                        THEN search_vector || setweight(COALESCE(notes_vector, ''), 'C')
+                        ELSE search_vector
+                    END,
+                    query
+                ) AS rank,
+                -- HTML-escape the source so the only markup in the headline is <mark>
+                ts_headline(
+                    'spanish_unaccent',
+                    replace(replace(replace(replace(replace(
+                        concat_ws(' — ', vision_result->>'name', vision_result->>'description',
+                                  CASE WHEN owned THEN notes END,
+                                  vision_result->>'broader_context'),
+                        '&', '&amp;'), '<', '&lt;'), '>', '&gt;'),
+                        '\"', '&quot;'), '''', '&#39;'),
+                    query,
+                    'StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=25, MinWords=8'
+                ) AS headline
+            FROM captures,
+                websearch_to_tsquery('spanish_unaccent', $1) AS query,
+                LATERAL (SELECT COALESCE(captures.user_id = $5, false) AS owned) AS viewer
+            WHERE is_deleted = false
+              AND (search_vector @@ query OR (owned AND notes_vector @@ query))
+              AND ($2::uuid IS NULL OR user_id = $2)
+            ORDER BY rank DESC, created_at DESC, id DESC
+            LIMIT $3 OFFSET $4
+        "
+                ),
+                &[&text, &user_id, &limit_i64, &offset_i64, &viewer],
             )
             .await?;
 
-        Ok(result > 0)
-    }
-
-    /// Publish a capture (set is_public = true)
-    pub async fn publish_capture(
-        &self,
-        id: &Uuid,
-    ) -> Result<Option<Capture>, Box<dyn std::error::Error + Send + Sync>> {
-        let client = self.get_client().await?;
-
-        let row = client.query_opt("
-            UPDATE captures SET is_public = true, updated_at = NOW()
-            WHERE id = $1 AND is_deleted = false
-            RETURNING id, user_id, author_name, device_local_id, image_url, thumbnail_url, image_size, storage_type,
-                      vision_result, category, confidence, tags, location, location_info, orientation,
-                      is_deleted, created_at, updated_at, difficulty, verified, is_public
-        ", &[id]).await?;
-
-        Ok(row.map(|r| Self::row_to_capture(&r)))
-    }
-
-    /// Unpublish a capture (set is_public = false)
-    pub async fn unpublish_capture(
-        &self,
-        id: &Uuid,
-    ) -> Result<Option<Capture>, Box<dyn std::error::Error + Send + Sync>> {
-        let client = self.get_client().await?;
-
-        let row = client.query_opt("
-            UPDATE captures SET is_public = false, updated_at = NOW()
-            WHERE id = $1 AND is_deleted = false
-            RETURNING id, user_id, author_name, device_local_id, image_url, thumbnail_url, image_size, storage_type,
-                      vision_result, category, confidence, tags, location, location_info, orientation,
-                      is_deleted, created_at, updated_at, difficulty, verified, is_public
-        ", &[id]).await?;
-
-        Ok(row.map(|r| Self::row_to_capture(&r)))
+        Ok(rows
+            .iter()
+            .map(|row| (Self::row_to_capture(row), row.get("rank"), row.get("headline")))
+            .collect())
     }
 
-    /// Hard delete capture (permanently remove from DB)
-    pub async fn hard_delete_capture(
+    /// Active captures within `radius_m` meters of a point, nearest first
+    pub async fn get_captures_nearby(
         &self,
-        id: &Uuid,
-    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        lat: f64,
+        lon: f64,
+        radius_m: f64,
+        user_id: Option<Uuid>,
+        limit: i32,
+    ) -> Result<Vec<(Capture, f64)>, Box<dyn std::error::Error + Send + Sync>> {
         let client = self.get_client().await?;
+        let limit_i64 = limit as i64;
 
-        let result = client
-            .execute(
-                "
-            DELETE FROM captures WHERE id = $1
-        ",
-                &[id],
+        // earth_box is a cube around the circle (uses the GiST index);
+        // earth_distance then trims its corners
+        let rows = client
+            .query(
+                &format!(
+                    "
+            SELECT {CAPTURE_COLUMNS},
+                earth_distance(ll_to_earth($1, $2), ll_to_earth(latitude, longitude)) AS distance_m
+            FROM captures
+            WHERE is_deleted = false
+              AND latitude IS NOT NULL
+              AND earth_box(ll_to_earth($1, $2), $3) @> ll_to_earth(latitude, longitude)
+              AND earth_distance(ll_to_earth($1, $2), ll_to_earth(latitude, longitude)) <= $3
+              AND ($4::uuid IS NULL OR user_id = $4)
+            ORDER BY distance_m ASC, id ASC
+            LIMIT $5
+        "
+                ),
+                &[&lat, &lon, &radius_m, &user_id, &limit_i64],
             )
             .await?;
 
-        Ok(result > 0)
+        Ok(rows
+            .iter()
+            .map(|row| (Self::row_to_capture(row), row.get("distance_m")))
+            .collect())
     }
 
-    /// Enqueue capture for analysis
-    pub async fn enqueue_analysis(
+    /// Active captures inside a bounding box, nearest to `query.reference_point()` first
+    pub async fn get_captures_in_bbox(
         &self,
-        capture_id: &Uuid,
-    ) -> Result<Uuid, Box<dyn std::error::Error + Send + Sync>> {
+        query: &BboxQuery,
+        limit: i32,
+    ) -> Result<Vec<(Capture, f64)>, Box<dyn std::error::Error + Send + Sync>> {
         let client = self.get_client().await?;
+        let limit_i64 = limit as i64;
+        let (ref_lat, ref_lon) = query.reference_point();
 
-        let id = Uuid::new_v4();
-        client
-            .execute(
-                "
-            INSERT INTO analysis_queue (id, capture_id, status, created_at)
-            VALUES ($1, $2, 'pending', NOW())
-            ON CONFLICT DO NOTHING
-        ",
-                &[&id, capture_id],
+        // A box crossing the antimeridian (min_lon > max_lon) wraps around
+        let rows = client
+            .query(
+                &format!(
+                    "
+            SELECT {CAPTURE_COLUMNS},
+                earth_distance(ll_to_earth($5, $6), ll_to_earth(latitude, longitude)) AS distance_m
+            FROM captures
+            WHERE is_deleted = false
+              AND latitude IS NOT NULL
+              AND latitude BETWEEN $1 AND $3
+              AND CASE WHEN $2 <= $4 THEN longitude BETWEEN $2 AND $4
+                       ELSE longitude >= $2 OR longitude <= $4 END
+              AND ($7::uuid IS NULL OR user_id = $7)
+            ORDER BY distance_m ASC, id ASC
+            LIMIT $8
+        "
+                ),
+                &[
+                    &query.min_lat,
+                    &query.min_lon,
+                    &query.max_lat,
+                    &query.max_lon,
+                    &ref_lat,
+                    &ref_lon,
+                    &query.user_id,
+                    &limit_i64,
+                ],
             )
             .await?;
 
-        Ok(id)
+        Ok(rows
+            .iter()
+            .map(|row| (Self::row_to_capture(row), row.get("distance_m")))
+            .collect())
     }
 
-    /// Get pending analysis tasks
-    pub async fn get_pending_analysis(
+    /// Active captures inside `bounds` grouped into a grid of `cell_size_deg` degrees,
+    /// largest groups first
+    pub async fn get_capture_clusters(
         &self,
+        bounds: &BboxQuery,
+        cell_size_deg: f64,
         limit: i32,
-    ) -> Result<Vec<Uuid>, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<Vec<CaptureCluster>, Box<dyn std::error::Error + Send + Sync>> {
         let client = self.get_client().await?;
         let limit_i64 = limit as i64;
 
+        // The representative is the capture with a thumbnail and the highest
+        // confidence; the three array_agg use the same order so they pick the same row
         let rows = client
             .query(
                 "
-            SELECT capture_id FROM analysis_queue
-            WHERE status = 'pending' AND (attempts < 3 OR attempts IS NULL)
-            ORDER BY created_at ASC LIMIT $1
+            WITH located AS (
+                SELECT id, latitude, longitude, category, thumbnail_key, is_public,
+                    confidence, created_at,
+                    floor(longitude / $5) AS cell_x,
+                    floor(latitude / $5) AS cell_y
+                FROM captures
+                WHERE is_deleted = false
+                  AND latitude IS NOT NULL
+                  AND latitude BETWEEN $1 AND $3
+                  AND CASE WHEN $2 <= $4 THEN longitude BETWEEN $2 AND $4
+                           ELSE longitude >= $2 OR longitude <= $4 END
+                  AND ($6::uuid IS NULL OR user_id = $6)
+            )
+            SELECT COUNT(*) AS count,
+                AVG(latitude) AS latitude,
+                AVG(longitude) AS longitude,
+                mode() WITHIN GROUP (ORDER BY category)
+                    FILTER (WHERE category IS NOT NULL) AS top_category,
+                (array_agg(id ORDER BY thumbnail_key IS NULL, confidence DESC NULLS LAST,
+                    created_at DESC))[1] AS representative_id,
+                (array_agg(thumbnail_key ORDER BY thumbnail_key IS NULL,
+                    confidence DESC NULLS LAST, created_at DESC))[1] AS thumbnail_key,
+                (array_agg(is_public ORDER BY thumbnail_key IS NULL,
+                    confidence DESC NULLS LAST, created_at DESC))[1] AS is_public
+            FROM located
+            GROUP BY cell_x, cell_y
+            ORDER BY count DESC
+            LIMIT $7
         ",
-                &[&limit_i64],
+                &[
+                    &bounds.min_lat,
+                    &bounds.min_lon,
+                    &bounds.max_lat,
+                    &bounds.max_lon,
+                    &cell_size_deg,
+                    &bounds.user_id,
+                    &limit_i64,
+                ],
             )
             .await?;
 
-        Ok(rows.iter().map(|row| row.get(0)).collect())
+        Ok(rows
+            .iter()
+            .map(|row| CaptureCluster {
+                count: row.get("count"),
+                latitude: row.get("latitude"),
+                longitude: row.get("longitude"),
+                top_category: row.get("top_category"),
+                representative_id: row.get("representative_id"),
+                thumbnail_url: None,
+                thumbnail_key: row.get("thumbnail_key"),
+                representative_is_public: row.get("is_public"),
+            })
+            .collect())
     }
 
-    /// Mark analysis as completed
-    pub async fn mark_analysis_completed(
-        &self,
-        capture_id: &Uuid,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let client = self.get_client().await?;
+    /// WHERE clause (over active captures) and its parameters for a listing query
+    fn capture_filter(query: &CaptureListQuery) -> (String, Vec<SqlParam>) {
+        let mut conditions = vec!["is_deleted = false".to_string()];
+        let mut params: Vec<SqlParam> = Vec::new();
 
-        client
-            .execute(
-                "
-            UPDATE analysis_queue SET status = 'completed', last_attempt = NOW()
-            WHERE capture_id = $1
-        ",
-                &[capture_id],
-            )
-            .await?;
+        let mut push = |condition: &str, param: SqlParam| {
+            params.push(param);
+            conditions.push(condition.replace("$?", &format!("${}", params.len())));
+        };
 
-        Ok(())
+        if let Some(user_id) = query.user_id {
+            push("user_id = $?", Box::new(user_id));
+        }
+        if let Some(category) = &query.category {
+            push("category = $?", Box::new(category.to_uppercase()));
+        }
+        let tags = query.tag_list();
+        if !tags.is_empty() {
+            match query.tags_match {
+                TagMatch::Any => push("tags && $?", Box::new(tags)),
+                TagMatch::All => push("tags @> $?", Box::new(tags)),
+            }
+        }
+        if let Some(difficulty) = &query.difficulty {
+            push("difficulty = $?", Box::new(difficulty.to_uppercase()));
+        }
+        if let Some(verified) = query.verified {
+            push("verified = $?", Box::new(verified));
+        }
+        if let Some(is_public) = query.is_public {
+            push("is_public = $?", Box::new(is_public));
+        }
+        if let Some(created_from) = query.created_from {
+            push("created_at >= $?", Box::new(created_from));
+        }
+        if let Some(created_to) = query.created_to {
+            push("created_at < $?", Box::new(created_to));
+        }
+        if let Some(min_confidence) = query.min_confidence {
+            push("confidence >= $?", Box::new(min_confidence));
+        }
+
+        (conditions.join(" AND "), params)
     }
 
-    /// Increment analysis attempts counter
-    pub async fn increment_analysis_attempts(
-        &self,
-        capture_id: &Uuid,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let client = self.get_client().await?;
+    /// ORDER BY of a listing. Confidence may be NULL (not analyzed yet); those go
+    /// last in both orders. The id tie-breaker keeps pages stable between equal values.
+    fn capture_order(query: &CaptureListQuery) -> String {
+        let direction = query.order.as_sql();
+        let nulls = if query.sort == CaptureSort::Confidence {
+            " NULLS LAST"
+        } else {
+            ""
+        };
+        format!(
+            "{column} {direction}{nulls}, id {direction}",
+            column = query.sort.column()
+        )
+    }
 
-        client
-            .execute(
-                "
-            UPDATE analysis_queue SET attempts = attempts + 1, last_attempt = NOW()
-            WHERE capture_id = $1
-        ",
-                &[capture_id],
-            )
-            .await?;
+    /// Rows strictly after `cursor` in its sort order. Confidence sorts NULLs last
+    /// in both directions, so a NULL cursor value only moves along the id.
+    fn keyset_condition(cursor: &CaptureCursor, params: &mut Vec<SqlParam>) -> String {
+        let op = match cursor.order {
+            SortOrder::Asc => ">",
+            SortOrder::Desc => "<",
+        };
+        let column = cursor.sort.column();
 
-        Ok(())
+        params.push(Box::new(cursor.id));
+        let id_idx = params.len();
+
+        match cursor.sort {
+            CaptureSort::CreatedAt | CaptureSort::UpdatedAt => {
+                params.push(Box::new(cursor.timestamp));
+                let value_idx = params.len();
+                format!("({column}, id) {op} (${value_idx}, ${id_idx})")
+            }
+            CaptureSort::Confidence => match cursor.confidence {
+                Some(confidence) => {
+                    params.push(Box::new(confidence));
+                    let value_idx = params.len();
+                    format!(
+                        "({column} {op} ${value_idx} \
+                         OR ({column} = ${value_idx} AND id {op} ${id_idx}) \
+                         OR {column} IS NULL)"
+                    )
+                }
+                None => format!("({column} IS NULL AND id {op} ${id_idx})"),
+            },
+        }
     }
 
-    /// Update capture with analysis result
-    pub async fn update_capture_analysis(
+    fn param_refs(params: &[SqlParam]) -> Vec<&(dyn ToSql + Sync)> {
+        params
+            .iter()
+            .map(|p| p.as_ref() as &(dyn ToSql + Sync))
+            .collect()
+    }
+
+    /// Update capture
+    pub async fn update_capture(
         &self,
-        capture_id: &Uuid,
-        vision_result: &serde_json::Value,
-        category: &str,
-        confidence: f64,
-        difficulty: &str,
-        verified: bool,
-        tags: Option<&Vec<String>>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let client = self.get_client().await?;
+        id: &Uuid,
+        req: &crate::models::UpdateCaptureRequest,
+        expected_versions: Option<&[i32]>,
+        actor: &str,
+    ) -> Result<CaptureUpdate, Box<dyn std::error::Error + Send + Sync>> {
+        let mut client = self.get_client().await?;
+        let tx = client.transaction().await?;
+        let now = Utc::now();
 
-        let tags_param = tags.cloned();
+        let before = match Self::lock_capture(&tx, id).await? {
+            Some(before) if !before.is_deleted => before,
+            _ => return Ok(CaptureUpdate::NotFound),
+        };
+        if let Some(mismatch) = Self::version_mismatch(&before, expected_versions) {
+            return Ok(mismatch);
+        }
 
-        let result = client
-            .execute(
-                "
-            UPDATE captures 
-            SET vision_result = $2, 
-                category = $3, 
-                confidence = $4, 
-                difficulty = $5, 
-                verified = $6,
-                tags = $7,
-                updated_at = NOW()
+        // Fields the analysis worker must no longer overwrite
+        let edited_fields: Vec<&str> = [
+            ("tags", req.tags.is_set()),
+            ("category", req.category.is_set()),
+            ("difficulty", req.difficulty.is_set()),
+        ]
+        .into_iter()
+        .filter_map(|(field, set)| set.then_some(field))
+        .collect();
+
+        // location_info is merged into the current object, not replaced
+        let location_info = req.location_info.value().map(|patch| {
+            let mut merged = before.location_info.clone().unwrap_or_default();
+            apply_merge_patch(&mut merged, patch);
+            merged
+        });
+
+        // Each field is only written when the patch includes it; an empty string
+        // also clears the notes
+        let row = tx.query_one(&format!("
+            UPDATE captures SET
+                tags = CASE WHEN $4 THEN $5 ELSE tags END,
+                category = CASE WHEN $6 THEN $7 ELSE category END,
+                notes = CASE WHEN $8 THEN NULLIF($9, '') ELSE notes END,
+                author_name = CASE WHEN $10 THEN $11 ELSE author_name END,
+                location_info = CASE WHEN $12 THEN $13 ELSE location_info END,
+                difficulty = CASE WHEN $14 THEN $15 ELSE difficulty END,
+                user_edited_fields = ARRAY(
+                    SELECT DISTINCT f FROM unnest(user_edited_fields || $3::text[]) AS f ORDER BY f
+                ),
+                updated_at = $2
             WHERE id = $1
-        ",
-                &[
-                    capture_id,
-                    vision_result,
-                    &category,
-                    &confidence,
-                    &difficulty,
-                    &verified,
-                    &tags_param,
-                ],
-            )
-            .await;
+            RETURNING {CAPTURE_COLUMNS}
+        "), &[
+            id,
+            &now,
+            &edited_fields,
+            &req.tags.is_set(),
+            &req.tags.value(),
+            &req.category.is_set(),
+            &req.category.value(),
+            &req.notes.is_set(),
+            &req.notes.value(),
+            &req.author_name.is_set(),
+            &req.author_name.value(),
+            &req.location_info.is_set(),
+            &location_info,
+            &req.difficulty.is_set(),
+            &req.difficulty.value(),
+        ]).await?;
+        let capture = Self::row_to_capture(&row);
 
-        match result {
-            Ok(rows) => {
-                if rows == 0 {
-                    log::warn!("No rows updated for capture {}", capture_id);
-                }
-                Ok(())
-            }
-            Err(e) => {
-                log::error!("Database error updating capture {}: {:?}", capture_id, e);
-                Err(Box::new(e))
-            }
+        // Keep the normalised tag tables in step with the edited array
+        if req.tags.is_set() {
+            let tags = req.tags.value().map(Vec::as_slice).unwrap_or_default();
+            Self::sync_capture_tags(&tx, id, tags).await?;
         }
-    }
-
-    /// Upsert a tag (insert if not exists, return existing if it does)
-    pub async fn upsert_tag(
-        &self,
-        name: &str,
-    ) -> Result<Uuid, Box<dyn std::error::Error + Send + Sync>> {
-        let client = self.get_client().await?;
 
-        let row = client
-            .query_one(
+        if req.notes.is_set() && capture.notes != before.notes {
+            tx.execute(
                 "
-            INSERT INTO tags (name) VALUES ($1)
-            ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name
-            RETURNING id
+            INSERT INTO capture_note_revisions (capture_id, notes, created_at)
+            VALUES ($1, $2, $3)
         ",
-                &[&name],
+                &[id, &capture.notes, &now],
             )
             .await?;
+        }
+
+        let changes = Self::capture_changes(Some(&before), Some(&capture));
+        Self::record_capture_event(&tx, id, actor, CaptureAction::Updated, &changes).await?;
 
-        Ok(row.get(0))
+        tx.commit().await?;
+        Ok(CaptureUpdate::Updated(Box::new(capture)))
     }
 
-    /// Insert a capture-tag relationship
-    pub async fn insert_capture_tag(
+    /// Saved versions of the notes of a capture, newest first
+    pub async fn get_note_revisions(
         &self,
         capture_id: &Uuid,
-        tag_id: &Uuid,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        offset: i64,
+        limit: i32,
+    ) -> Result<Vec<crate::models::NoteRevision>, Box<dyn std::error::Error + Send + Sync>> {
         let client = self.get_client().await?;
+        let limit_i64 = limit as i64;
 
-        client
-            .execute(
+        let rows = client
+            .query(
                 "
-            INSERT INTO capture_tags (capture_id, tag_id) 
-            VALUES ($1, $2) 
-            ON CONFLICT DO NOTHING
+            SELECT id, notes, created_at FROM capture_note_revisions
+            WHERE capture_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2 OFFSET $3
         ",
-                &[capture_id, tag_id],
+                &[capture_id, &limit_i64, &offset],
             )
             .await?;
 
-        Ok(())
+        Ok(rows
+            .iter()
+            .map(|row| crate::models::NoteRevision {
+                id: row.get(0),
+                notes: row.get(1),
+                created_at: row.get(2),
+            })
+            .collect())
     }
 
-    /// Get all tags for a capture
-    pub async fn get_tags_for_capture(
+    /// History of a capture, newest first. Available after a hard delete too.
+    pub async fn get_capture_events(
         &self,
         capture_id: &Uuid,
-    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        offset: i64,
+        limit: i32,
+    ) -> Result<Vec<CaptureEvent>, Box<dyn std::error::Error + Send + Sync>> {
         let client = self.get_client().await?;
+        let limit_i64 = limit as i64;
 
         let rows = client
             .query(
                 "
-            SELECT t.name FROM tags t
-            JOIN capture_tags ct ON t.id = ct.tag_id
-            WHERE ct.capture_id = $1
-            ORDER BY t.name
+            SELECT id, capture_id, actor, action, changes, created_at FROM capture_events
+            WHERE capture_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2 OFFSET $3
         ",
-                &[capture_id],
+                &[capture_id, &limit_i64, &offset],
             )
             .await?;
 
-        Ok(rows.iter().map(|r| r.get(0)).collect())
+        Ok(rows
+            .iter()
+            .map(|row| CaptureEvent {
+                id: row.get(0),
+                capture_id: row.get(1),
+                actor: row.get(2),
+                action: row.get(3),
+                changes: row.get(4),
+                created_at: row.get(5),
+            })
+            .collect())
     }
 
-    /// Save tags for a capture (replaces existing tags)
-    pub async fn save_capture_tags(
-        &self,
-        capture_id: &Uuid,
-        tag_names: &[String],
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Delete existing tags for this capture
-        let client = self.get_client().await?;
-        client
-            .execute(
-                "DELETE FROM capture_tags WHERE capture_id = $1",
-                &[capture_id],
+    /// Lock a capture (in the trash or not) for the rest of the transaction
+    async fn lock_capture(
+        tx: &tokio_postgres::Transaction<'_>,
+        id: &Uuid,
+    ) -> Result<Option<Capture>, Box<dyn std::error::Error + Send + Sync>> {
+        let row = tx
+            .query_opt(
+                &format!("SELECT {CAPTURE_COLUMNS} FROM captures WHERE id = $1 FOR UPDATE"),
+                &[id],
             )
             .await?;
 
-        // Insert new tags
-        for tag_name in tag_names {
-            let tag_id = self.upsert_tag(tag_name).await?;
-            self.insert_capture_tag(capture_id, &tag_id).await?;
-        }
-
-        Ok(())
+        Ok(row.map(|r| Self::row_to_capture(&r)))
     }
 
-    /// Get all unique tags in the system
-    pub async fn get_all_tags(
+    /// Run `sql` (an `UPDATE ... WHERE id = $1 ... RETURNING {CAPTURE_COLUMNS}`) and
+    /// record the change in the capture's history. With `expected_versions` the
+    /// update only runs if the current version is one of them.
+    async fn update_capture_with_event(
         &self,
-    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
-        let client = self.get_client().await?;
+        id: &Uuid,
+        expected_versions: Option<&[i32]>,
+        actor: &str,
+        action: CaptureAction,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<CaptureUpdate, Box<dyn std::error::Error + Send + Sync>> {
+        let mut client = self.get_client().await?;
+        let tx = client.transaction().await?;
+
+        let result =
+            Self::update_capture_in_tx(&tx, id, expected_versions, actor, action, sql, params)
+                .await?;
+        if let CaptureUpdate::Updated(_) = result {
+            tx.commit().await?;
+        }
+        Ok(result)
+    }
+
+    /// `update_capture_with_event` inside a caller's transaction, for updates that
+    /// must commit together with other writes
+    async fn update_capture_in_tx(
+        tx: &tokio_postgres::Transaction<'_>,
+        id: &Uuid,
+        expected_versions: Option<&[i32]>,
+        actor: &str,
+        action: CaptureAction,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<CaptureUpdate, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(before) = Self::lock_capture(tx, id).await? else {
+            return Ok(CaptureUpdate::NotFound);
+        };
+        if let Some(mismatch) = Self::version_mismatch(&before, expected_versions) {
+            return Ok(mismatch);
+        }
+        let Some(row) = tx.query_opt(sql, params).await? else {
+            return Ok(CaptureUpdate::NotFound);
+        };
+        let after = Self::row_to_capture(&row);
+
+        let changes = if action.records_changes() {
+            Self::capture_changes(Some(&before), Some(&after))
+        } else {
+            serde_json::json!({})
+        };
+        Self::record_capture_event(tx, id, actor, action, &changes).await?;
+
+        Ok(CaptureUpdate::Updated(Box::new(after)))
+    }
+
+    fn version_mismatch(
+        capture: &Capture,
+        expected_versions: Option<&[i32]>,
+    ) -> Option<CaptureUpdate> {
+        match expected_versions {
+            Some(versions) if !versions.contains(&capture.version) => {
+                Some(CaptureUpdate::VersionMismatch {
+                    current_version: capture.version,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Append an event to the history of a capture. Mutations that changed
+    /// nothing (e.g. publishing a public capture) are not recorded; deletions are
+    /// always recorded, without a diff.
+    async fn record_capture_event(
+        tx: &tokio_postgres::Transaction<'_>,
+        capture_id: &Uuid,
+        actor: &str,
+        action: CaptureAction,
+        changes: &serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if action.records_changes() && changes.as_object().is_some_and(|c| c.is_empty()) {
+            return Ok(());
+        }
+
+        tx.execute(
+            "
+            INSERT INTO capture_events (capture_id, actor, action, changes)
+            VALUES ($1, $2, $3, $4)
+        ",
+            &[capture_id, &actor, &action.as_str(), changes],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// `{"field": {"before": ..., "after": ...}}` for every audited field that differs.
+    /// A missing side (creation) counts as all nulls. Values of `REDACTED_FIELDS`
+    /// are replaced by `"[redacted]"`, keeping only whether they were set.
+    fn capture_changes(before: Option<&Capture>, after: Option<&Capture>) -> serde_json::Value {
+        let fields = |capture: Option<&Capture>| match capture.map(serde_json::to_value) {
+            Some(Ok(serde_json::Value::Object(map))) => map,
+            _ => serde_json::Map::new(),
+        };
+        let before = fields(before);
+        let after = fields(after);
+
+        let mut names: Vec<&String> = before.keys().chain(after.keys()).collect();
+        names.sort();
+        names.dedup();
+
+        let mut changes = serde_json::Map::new();
+        for name in names {
+            if UNAUDITED_FIELDS.contains(&name.as_str()) {
+                continue;
+            }
+            let old = before.get(name).unwrap_or(&serde_json::Value::Null);
+            let new = after.get(name).unwrap_or(&serde_json::Value::Null);
+            if old == new {
+                continue;
+            }
+            let (old, new) = if REDACTED_FIELDS.contains(&name.as_str()) {
+                (Self::redact(old), Self::redact(new))
+            } else {
+                (old.clone(), new.clone())
+            };
+            changes.insert(
+                name.clone(),
+                serde_json::json!({ "before": old, "after": new }),
+            );
+        }
+
+        serde_json::Value::Object(changes)
+    }
+
+    fn redact(value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Null => serde_json::Value::Null,
+            _ => serde_json::Value::from("[redacted]"),
+        }
+    }
+
+    /// Soft delete capture (move it to the trash) if it belongs to `user_id`
+    pub async fn delete_capture(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid,
+        actor: &str,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let result = self
+            .update_capture_with_event(
+                id,
+                None,
+                actor,
+                CaptureAction::Deleted,
+                &format!(
+                    "
+            UPDATE captures SET is_deleted = true, deleted_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND is_deleted = false
+            RETURNING {CAPTURE_COLUMNS}
+        "
+                ),
+                &[id, user_id],
+            )
+            .await?;
+
+        Ok(result.updated().is_some())
+    }
+
+    /// Restore a capture from the trash if it belongs to `user_id`
+    pub async fn restore_capture(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid,
+        actor: &str,
+    ) -> Result<Option<Capture>, Box<dyn std::error::Error + Send + Sync>> {
+        let result = self
+            .update_capture_with_event(
+                id,
+                None,
+                actor,
+                CaptureAction::Restored,
+                &format!(
+                    "
+            UPDATE captures SET is_deleted = false, deleted_at = NULL, updated_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND is_deleted = true
+            RETURNING {CAPTURE_COLUMNS}
+        "
+                ),
+                &[id, user_id],
+            )
+            .await?;
+
+        Ok(result.updated())
+    }
+
+    /// Get capture by ID whether or not it is in the trash
+    pub async fn get_capture_including_deleted(
+        &self,
+        id: &Uuid,
+    ) -> Result<Option<Capture>, Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+
+        let row = client
+            .query_opt(
+                &format!(
+                    "
+            SELECT {CAPTURE_COLUMNS}
+            FROM captures WHERE id = $1
+        "
+                ),
+                &[id],
+            )
+            .await?;
+
+        Ok(row.map(|r| Self::row_to_capture(&r)))
+    }
+
+    /// Get a user's trashed captures, most recently deleted first
+    pub async fn get_trashed_captures(
+        &self,
+        user_id: &Uuid,
+        page: i32,
+        limit: i32,
+    ) -> Result<(Vec<Capture>, i64), Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+        let limit_i64 = limit as i64;
+        let offset_i64 = (page as i64 - 1) * limit_i64;
+
+        let total: i64 = client
+            .query_one(
+                "SELECT COUNT(*) FROM captures WHERE user_id = $1 AND is_deleted = true",
+                &[user_id],
+            )
+            .await?
+            .get(0);
+
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {CAPTURE_COLUMNS}
+             FROM captures WHERE user_id = $1 AND is_deleted = true
+             ORDER BY deleted_at DESC NULLS LAST LIMIT $2 OFFSET $3"
+                ),
+                &[user_id, &limit_i64, &offset_i64],
+            )
+            .await?;
+
+        let captures = rows.iter().map(Self::row_to_capture).collect();
+
+        Ok((captures, total))
+    }
+
+    /// Trashed captures whose retention period has elapsed
+    pub async fn get_expired_trash(
+        &self,
+        retention_days: i32,
+        limit: i32,
+    ) -> Result<Vec<Capture>, Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+        let limit_i64 = limit as i64;
+
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {CAPTURE_COLUMNS}
+             FROM captures
+             WHERE is_deleted = true
+               AND COALESCE(deleted_at, updated_at) < NOW() - make_interval(days => $1)
+             ORDER BY deleted_at ASC NULLS FIRST LIMIT $2"
+                ),
+                &[&retention_days, &limit_i64],
+            )
+            .await?;
+
+        Ok(rows.iter().map(Self::row_to_capture).collect())
+    }
+
+    /// Publish a capture (set is_public = true)
+    pub async fn publish_capture(
+        &self,
+        id: &Uuid,
+        expected_versions: Option<&[i32]>,
+        actor: &str,
+    ) -> Result<CaptureUpdate, Box<dyn std::error::Error + Send + Sync>> {
+        self.update_capture_with_event(
+            id,
+            expected_versions,
+            actor,
+            CaptureAction::Published,
+            &format!(
+                "
+            UPDATE captures SET is_public = true, updated_at = NOW()
+            WHERE id = $1 AND is_deleted = false
+            RETURNING {CAPTURE_COLUMNS}
+        "
+            ),
+            &[id],
+        )
+        .await
+    }
+
+    /// Unpublish a capture (set is_public = false)
+    pub async fn unpublish_capture(
+        &self,
+        id: &Uuid,
+        expected_versions: Option<&[i32]>,
+        actor: &str,
+    ) -> Result<CaptureUpdate, Box<dyn std::error::Error + Send + Sync>> {
+        self.update_capture_with_event(
+            id,
+            expected_versions,
+            actor,
+            CaptureAction::Unpublished,
+            &format!(
+                "
+            UPDATE captures SET is_public = false, updated_at = NOW()
+            WHERE id = $1 AND is_deleted = false
+            RETURNING {CAPTURE_COLUMNS}
+        "
+            ),
+            &[id],
+        )
+        .await
+    }
+
+    /// Hard delete capture (permanently remove from DB)
+    pub async fn hard_delete_capture(
+        &self,
+        id: &Uuid,
+        extra_object_keys: &[String],
+        actor: &str,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.remove_capture(id, extra_object_keys, actor, None).await
+    }
+
+    /// Hard delete a capture that is still in the trash past `retention_days`.
+    /// Returns false if it was restored (or purged) since it was listed.
+    pub async fn purge_trashed_capture(
+        &self,
+        id: &Uuid,
+        extra_object_keys: &[String],
+        retention_days: i32,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        self.remove_capture(id, extra_object_keys, ACTOR_TRASH_PURGE, Some(retention_days))
+            .await
+    }
+
+    async fn remove_capture(
+        &self,
+        id: &Uuid,
+        extra_object_keys: &[String],
+        actor: &str,
+        expired_after_days: Option<i32>,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut client = self.get_client().await?;
+        let tx = client.transaction().await?;
+
+        let Some(before) = Self::lock_capture(&tx, id).await? else {
+            return Ok(false);
+        };
+
+        // Re-check on the locked row: the capture may have been restored meanwhile
+        if let Some(days) = expired_after_days {
+            let cutoff = Utc::now() - chrono::Duration::days(days as i64);
+            let trashed_at = before.deleted_at.unwrap_or(before.updated_at);
+            if !before.is_deleted || trashed_at >= cutoff {
+                return Ok(false);
+            }
+        }
+
+        // Queue the capture's objects (image, thumbnail, renditions, plus keys the
+        // caller derived from legacy URLs) in the same transaction as the delete
+        tx.execute(
+            "
+            INSERT INTO storage_deletions (object_key, capture_id)
+            SELECT DISTINCT object_key, $1 FROM (
+                SELECT image_key AS object_key FROM captures WHERE id = $1
+                UNION SELECT thumbnail_key FROM captures WHERE id = $1
+                UNION SELECT object_key FROM capture_renditions WHERE capture_id = $1
+                UNION SELECT unnest($2::text[])
+            ) keys
+            WHERE object_key IS NOT NULL
+            ON CONFLICT (object_key) WHERE status = 'pending' DO NOTHING
+        ",
+            &[id, &extra_object_keys],
+        )
+        .await?;
+
+        let result = tx
+            .execute(
+                "
+            DELETE FROM captures WHERE id = $1
+        ",
+                &[id],
+            )
+            .await?;
+
+        if result == 0 {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+
+        Self::record_capture_event(&tx, id, actor, CaptureAction::Purged, &serde_json::json!({}))
+            .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Claim pending storage deletions that are due
+    pub async fn claim_storage_deletions(
+        &self,
+        limit: i32,
+    ) -> Result<Vec<StorageDeletion>, Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+        let limit_i64 = limit as i64;
+
+        let rows = client
+            .query(
+                "
+            UPDATE storage_deletions SET run_after = NOW() + INTERVAL '5 minutes'
+            WHERE id IN (
+                SELECT id FROM storage_deletions
+                WHERE status = 'pending' AND run_after <= NOW()
+                ORDER BY run_after ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, object_key, attempts
+        ",
+                &[&limit_i64],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| StorageDeletion {
+                id: row.get(0),
+                object_key: row.get(1),
+                attempts: row.get(2),
+            })
+            .collect())
+    }
+
+    /// Mark a storage deletion as done
+    pub async fn complete_storage_deletion(
+        &self,
+        id: &Uuid,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+        client
+            .execute(
+                "
+            UPDATE storage_deletions SET status = 'completed', completed_at = NOW()
+            WHERE id = $1
+        ",
+                &[id],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Reschedule a failed storage deletion; marks it failed at `max_attempts`
+    pub async fn retry_storage_deletion(
+        &self,
+        id: &Uuid,
+        error_message: &str,
+        delay_seconds: i64,
+        max_attempts: i32,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+        let delay = delay_seconds as f64;
+
+        client
+            .execute(
+                "
+            UPDATE storage_deletions SET
+                attempts = attempts + 1,
+                last_error = $2,
+                status = CASE WHEN attempts + 1 >= $4 THEN 'failed' ELSE 'pending' END,
+                run_after = NOW() + make_interval(secs => $3)
+            WHERE id = $1
+        ",
+                &[id, &error_message, &delay, &max_attempts],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Enqueue a job for a capture. Re-enqueueing an existing job resets it to pending.
+    pub async fn enqueue_job(
+        &self,
+        capture_id: &Uuid,
+        job_type: JobType,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+
+        client
+            .execute(
+                "
+            INSERT INTO job_queue (capture_id, job_type, status, created_at, run_after)
+            VALUES ($1, $2, 'pending', NOW(), NOW())
+            ON CONFLICT (capture_id, job_type) DO UPDATE
+            SET status = 'pending', attempts = 0, error_message = NULL, run_after = NOW(), completed_at = NULL
+            WHERE job_queue.status <> 'running'
+        ",
+                &[capture_id, &job_type.as_str()],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Backfill the image jobs (thumbnail, EXIF, hash) of captures created before the typed
+    /// queue existed. Throttled: nothing is queued while `batch` or more image jobs are
+    /// still pending, so the bucket is re-downloaded a few captures at a time.
+    pub async fn enqueue_image_backfill(
+        &self,
+        batch: i32,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+        let batch_i64 = batch as i64;
+
+        let queued = client
+            .execute(
+                "
+            INSERT INTO job_queue (capture_id, job_type)
+            SELECT c.id, j.job_type
+            FROM (
+                SELECT id FROM captures c
+                WHERE is_deleted = false
+                  AND NOT EXISTS (
+                      SELECT 1 FROM job_queue q
+                      WHERE q.capture_id = c.id AND q.job_type IN ('thumbnail', 'exif', 'hash')
+                  )
+                  AND (
+                      SELECT count(*) FROM job_queue
+                      WHERE status = 'pending' AND job_type IN ('thumbnail', 'exif', 'hash')
+                  ) < $1
+                ORDER BY created_at DESC
+                LIMIT $1
+            ) c
+            CROSS JOIN (VALUES ('thumbnail'), ('exif'), ('hash')) AS j(job_type)
+            ON CONFLICT (capture_id, job_type) DO NOTHING
+        ",
+                &[&batch_i64],
+            )
+            .await?;
+
+        Ok(queued)
+    }
+
+    /// Claim due jobs for processing. Jobs left `running` by a crashed worker are reclaimed
+    /// after 15 minutes; `SKIP LOCKED` lets several instances share the queue.
+    /// The other due image jobs of a claimed capture are claimed along with it, so the
+    /// worker downloads the original once for all of them.
+    pub async fn claim_pending_jobs(
+        &self,
+        limit: i32,
+        max_attempts: i32,
+    ) -> Result<Vec<Job>, Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+        let limit_i64 = limit as i64;
+
+        let rows = client
+            .query(
+                "
+            WITH due AS (
+                SELECT id, capture_id, job_type FROM job_queue
+                WHERE attempts < $2
+                  AND ((status = 'pending' AND run_after <= NOW())
+                       OR (status = 'running' AND last_attempt < NOW() - INTERVAL '15 minutes'))
+                ORDER BY run_after ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            ),
+            siblings AS (
+                SELECT j.id FROM job_queue j
+                JOIN due d ON d.capture_id = j.capture_id
+                WHERE d.job_type IN ('thumbnail', 'exif', 'hash')
+                  AND j.job_type IN ('thumbnail', 'exif', 'hash')
+                  AND j.status = 'pending' AND j.run_after <= NOW() AND j.attempts < $2
+                FOR UPDATE OF j SKIP LOCKED
+            )
+            UPDATE job_queue SET status = 'running', last_attempt = NOW()
+            WHERE id IN (SELECT id FROM due UNION SELECT id FROM siblings)
+            RETURNING id, capture_id, job_type, attempts
+        ",
+                &[&limit_i64, &max_attempts],
+            )
+            .await?;
+
+        let mut jobs = Vec::with_capacity(rows.len());
+        for row in rows {
+            let job_type: String = row.get(2);
+            match job_type.parse::<JobType>() {
+                Ok(job_type) => jobs.push(Job {
+                    id: row.get(0),
+                    capture_id: row.get(1),
+                    job_type,
+                    attempts: row.get(3),
+                }),
+                Err(e) => log::warn!("Skipping job {}: {}", row.get::<_, Uuid>(0), e),
+            }
+        }
+
+        Ok(jobs)
+    }
+
+    /// Mark a job as completed
+    pub async fn mark_job_completed(
+        &self,
+        job_id: &Uuid,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+
+        client
+            .execute(
+                "
+            UPDATE job_queue SET status = 'completed', error_message = NULL, completed_at = NOW()
+            WHERE id = $1
+        ",
+                &[job_id],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Put a failed job back in the queue after `delay_seconds`.
+    /// When `count_attempt` is set the attempt is consumed and the job is marked
+    /// `failed` once it reaches `max_attempts`.
+    pub async fn retry_job(
+        &self,
+        job_id: &Uuid,
+        error_message: &str,
+        delay_seconds: i64,
+        count_attempt: bool,
+        max_attempts: i32,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+        let increment: i32 = if count_attempt { 1 } else { 0 };
+        let delay = delay_seconds as f64;
+
+        client
+            .execute(
+                "
+            UPDATE job_queue SET
+                attempts = attempts + $3,
+                error_message = $2,
+                status = CASE WHEN attempts + $3 >= $5 THEN 'failed' ELSE 'pending' END,
+                run_after = NOW() + make_interval(secs => $4)
+            WHERE id = $1
+        ",
+                &[job_id, &error_message, &increment, &delay, &max_attempts],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Store image metadata extracted by the exif job
+    pub async fn update_capture_image_metadata(
+        &self,
+        capture_id: &Uuid,
+        width: i32,
+        height: i32,
+        exif_orientation: Option<i16>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.update_capture_with_event(
+            capture_id,
+            None,
+            ACTOR_WORKER,
+            CaptureAction::Processed,
+            &format!("
+            UPDATE captures SET image_width = $2, image_height = $3, exif_orientation = $4, updated_at = NOW()
+            WHERE id = $1
+            RETURNING {CAPTURE_COLUMNS}
+        "),
+            &[capture_id, &width, &height, &exif_orientation],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Store the BlurHash and dominant colours computed by the thumbnail job
+    pub async fn update_capture_placeholder(
+        &self,
+        capture_id: &Uuid,
+        blurhash: &str,
+        dominant_colors: &[String],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.update_capture_with_event(
+            capture_id,
+            None,
+            ACTOR_WORKER,
+            CaptureAction::Processed,
+            &format!(
+                "
+            UPDATE captures SET blurhash = $2, dominant_colors = $3, updated_at = NOW()
+            WHERE id = $1
+            RETURNING {CAPTURE_COLUMNS}
+        "
+            ),
+            &[capture_id, &blurhash, &dominant_colors],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Store the content hash computed by the hash job (and the size if it was unknown)
+    pub async fn update_capture_content_hash(
+        &self,
+        capture_id: &Uuid,
+        content_hash: &str,
+        image_size: i64,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.update_capture_with_event(
+            capture_id,
+            None,
+            ACTOR_WORKER,
+            CaptureAction::Processed,
+            &format!("
+            UPDATE captures SET content_hash = $2, image_size = COALESCE(image_size, $3), updated_at = NOW()
+            WHERE id = $1
+            RETURNING {CAPTURE_COLUMNS}
+        "),
+            &[capture_id, &content_hash, &image_size],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Update capture with analysis result
+    pub async fn update_capture_analysis(
+        &self,
+        capture_id: &Uuid,
+        vision_result: &serde_json::Value,
+        category: &str,
+        confidence: f64,
+        difficulty: &str,
+        verified: bool,
+        tags: Option<&Vec<String>>,
+    ) -> Result<Option<Capture>, Box<dyn std::error::Error + Send + Sync>> {
+        let tags_param = tags.cloned();
+
+        // Category, difficulty and tags the user edited by hand are kept
+        let result = self
+            .update_capture_with_event(
+                capture_id,
+                None,
+                ACTOR_WORKER,
+                CaptureAction::Analyzed,
+                &format!(
+                    "
+            UPDATE captures 
+            SET vision_result = $2, 
+                category = CASE WHEN 'category' = ANY(user_edited_fields) THEN category ELSE $3 END,
+                confidence = $4, 
+                difficulty = CASE WHEN 'difficulty' = ANY(user_edited_fields)
+                    THEN difficulty ELSE $5 END,
+                verified = $6,
+                tags = CASE WHEN 'tags' = ANY(user_edited_fields) THEN tags ELSE $7 END,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING {CAPTURE_COLUMNS}
+        "
+                ),
+                &[
+                    capture_id,
+                    vision_result,
+                    &category,
+                    &confidence,
+                    &difficulty,
+                    &verified,
+                    &tags_param,
+                ],
+            )
+            .await;
+
+        match result.map(CaptureUpdate::updated) {
+            Ok(capture) => {
+                if capture.is_none() {
+                    log::warn!("No rows updated for capture {}", capture_id);
+                }
+                Ok(capture)
+            }
+            Err(e) => {
+                log::error!("Database error updating capture {}: {:?}", capture_id, e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Get all tags for a capture
+    pub async fn get_tags_for_capture(
+        &self,
+        capture_id: &Uuid,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+
+        let rows = client
+            .query(
+                "
+            SELECT t.name FROM tags t
+            JOIN capture_tags ct ON t.id = ct.tag_id
+            WHERE ct.capture_id = $1
+            ORDER BY t.name
+        ",
+                &[capture_id],
+            )
+            .await?;
+
+        Ok(rows.iter().map(|r| r.get(0)).collect())
+    }
+
+    /// Save tags for a capture (replaces existing tags)
+    pub async fn save_capture_tags(
+        &self,
+        capture_id: &Uuid,
+        tag_names: &[String],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut client = self.get_client().await?;
+        let tx = client.transaction().await?;
+        Self::sync_capture_tags(&tx, capture_id, tag_names).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Make `capture_tags` match `tag_names`, creating missing tags. Links that
+    /// are kept keep their original `created_at`.
+    async fn sync_capture_tags(
+        tx: &tokio_postgres::Transaction<'_>,
+        capture_id: &Uuid,
+        tag_names: &[String],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        tx.execute(
+            "
+            DELETE FROM capture_tags
+            WHERE capture_id = $1
+              AND tag_id NOT IN (SELECT id FROM tags WHERE name = ANY($2))
+        ",
+            &[capture_id, &tag_names],
+        )
+        .await?;
+
+        tx.execute(
+            "
+            INSERT INTO tags (name) SELECT DISTINCT unnest($1::text[])
+            ON CONFLICT (name) DO NOTHING
+        ",
+            &[&tag_names],
+        )
+        .await?;
+
+        tx.execute(
+            "
+            INSERT INTO capture_tags (capture_id, tag_id)
+            SELECT $1, id FROM tags WHERE name = ANY($2)
+            ON CONFLICT DO NOTHING
+        ",
+            &[capture_id, &tag_names],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Names of every tag
+    pub async fn get_all_tags(
+        &self,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
 
         let rows = client
             .query(
@@ -502,6 +1610,393 @@ impl DatabaseService {
         Ok(rows.iter().map(|r| r.get(0)).collect())
     }
 
+    /// Merge the `sources` tags into `target`: moves their `capture_tags` links,
+    /// rewrites the `tags` arrays of the affected captures and deletes the sources.
+    /// Returns the source tags that existed and the number of captures rewritten.
+    pub async fn merge_tags(
+        &self,
+        sources: &[String],
+        target: &str,
+        actor: &str,
+    ) -> Result<(Vec<String>, u64), Box<dyn std::error::Error + Send + Sync>> {
+        let sources: Vec<String> = sources.iter().filter(|s| *s != target).cloned().collect();
+        let mut client = self.get_client().await?;
+        let tx = client.transaction().await?;
+
+        let merged: Vec<String> = tx
+            .query(
+                "SELECT name FROM tags WHERE name = ANY($1) ORDER BY name FOR UPDATE",
+                &[&sources],
+            )
+            .await?
+            .iter()
+            .map(|r| r.get(0))
+            .collect();
+
+        tx.execute(
+            "
+            INSERT INTO tags (name) VALUES ($1)
+            ON CONFLICT (name) DO NOTHING
+        ",
+            &[&target],
+        )
+        .await?;
+
+        // Links move to the target keeping the earliest tagging time
+        tx.execute(
+            "
+            INSERT INTO capture_tags (capture_id, tag_id, created_at)
+            SELECT ct.capture_id, (SELECT id FROM tags WHERE name = $2), MIN(ct.created_at)
+            FROM capture_tags ct
+            JOIN tags t ON t.id = ct.tag_id
+            WHERE t.name = ANY($1)
+            GROUP BY ct.capture_id
+            ON CONFLICT (capture_id, tag_id) DO NOTHING
+        ",
+            &[&sources, &target],
+        )
+        .await?;
+
+        // Replace the sources in the arrays, dropping duplicates but keeping order,
+        // and record the change in the history of every rewritten capture
+        let affected = tx
+            .execute(
+                "
+            WITH previous AS (
+                SELECT id, tags AS old_tags FROM captures
+                WHERE tags && $1::text[]
+                FOR UPDATE
+            ),
+            rewritten AS (
+                UPDATE captures c SET tags = (
+                    SELECT array_agg(tag ORDER BY position)
+                    FROM (
+                        SELECT CASE WHEN t = ANY($1) THEN $2 ELSE t END AS tag,
+                            MIN(ord) AS position
+                        FROM unnest(c.tags) WITH ORDINALITY AS u(t, ord)
+                        GROUP BY 1
+                    ) deduped
+                )
+                FROM previous p
+                WHERE c.id = p.id
+                RETURNING c.id, p.old_tags, c.tags
+            )
+            INSERT INTO capture_events (capture_id, actor, action, changes)
+            SELECT id, $3, $4, jsonb_build_object(
+                'tags', jsonb_build_object('before', to_jsonb(old_tags), 'after', to_jsonb(tags))
+            )
+            FROM rewritten
+        ",
+                &[&sources, &target, &actor, &CaptureAction::TagsMerged.as_str()],
+            )
+            .await?;
+
+        tx.execute("DELETE FROM tags WHERE name = ANY($1)", &[&sources])
+            .await?;
+
+        tx.commit().await?;
+        Ok((merged, affected))
+    }
+
+    /// Tags with the number of active captures using them, most used first.
+    /// With `prefix` only tags starting with it (autocomplete).
+    pub async fn get_tag_counts(
+        &self,
+        prefix: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<TagCount>, Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+        let limit_i64 = limit as i64;
+
+        // Escape LIKE wildcards so the prefix is matched literally
+        let pattern = prefix.map(|p| {
+            format!(
+                "{}%",
+                p.replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+            )
+        });
+
+        let rows = client
+            .query(
+                "
+            SELECT t.name, COUNT(*) AS count
+            FROM tags t
+            JOIN capture_tags ct ON ct.tag_id = t.id
+            JOIN captures c ON c.id = ct.capture_id AND c.is_deleted = false
+            WHERE ($1::text IS NULL OR t.name LIKE $1)
+            GROUP BY t.name
+            ORDER BY count DESC, t.name ASC
+            LIMIT $2
+        ",
+                &[&pattern, &limit_i64],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| TagCount {
+                name: row.get(0),
+                count: row.get(1),
+            })
+            .collect())
+    }
+
+    /// Tags most used on captures tagged in the last `days` days, with the count
+    /// of the window before for comparison
+    pub async fn get_trending_tags(
+        &self,
+        days: i32,
+        limit: i32,
+    ) -> Result<Vec<TrendingTag>, Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+        let limit_i64 = limit as i64;
+
+        let rows = client
+            .query(
+                "
+            SELECT t.name,
+                COUNT(*) FILTER (WHERE ct.created_at >= NOW() - make_interval(days => $1)) AS count,
+                COUNT(*) FILTER (WHERE ct.created_at < NOW() - make_interval(days => $1))
+                    AS previous_count
+            FROM capture_tags ct
+            JOIN tags t ON t.id = ct.tag_id
+            JOIN captures c ON c.id = ct.capture_id AND c.is_deleted = false
+            WHERE ct.created_at >= NOW() - make_interval(days => $1 * 2)
+            GROUP BY t.name
+            HAVING COUNT(*) FILTER (WHERE ct.created_at >= NOW() - make_interval(days => $1)) > 0
+            ORDER BY count DESC, previous_count ASC, t.name ASC
+            LIMIT $2
+        ",
+                &[&days, &limit_i64],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| TrendingTag {
+                name: row.get(0),
+                count: row.get(1),
+                previous_count: row.get(2),
+            })
+            .collect())
+    }
+
+    /// Active captures with a tag, newest first
+    pub async fn get_captures_by_tag(
+        &self,
+        tag_name: &str,
+        page: i32,
+        limit: i32,
+    ) -> Result<(Vec<Capture>, i64), Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+        let limit_i64 = limit as i64;
+        let offset_i64 = (page as i64 - 1) * limit_i64;
+
+        let total: i64 = client
+            .query_one(
+                "
+            SELECT COUNT(*) FROM captures c
+            JOIN capture_tags ct ON ct.capture_id = c.id
+            JOIN tags t ON t.id = ct.tag_id
+            WHERE t.name = $1 AND c.is_deleted = false
+        ",
+                &[&tag_name],
+            )
+            .await?
+            .get(0);
+
+        let rows = client
+            .query(
+                &format!(
+                    "
+            SELECT {columns}
+            FROM captures c
+            JOIN capture_tags ct ON ct.capture_id = c.id
+            JOIN tags t ON t.id = ct.tag_id
+            WHERE t.name = $1 AND c.is_deleted = false
+            ORDER BY c.created_at DESC, c.id DESC
+            LIMIT $2 OFFSET $3
+        ",
+                    columns = Self::qualified_capture_columns("c")
+                ),
+                &[&tag_name, &limit_i64, &offset_i64],
+            )
+            .await?;
+
+        let captures = rows.iter().map(Self::row_to_capture).collect();
+
+        Ok((captures, total))
+    }
+
+    /// `CAPTURE_COLUMNS` prefixed with a table alias, for queries with joins
+    fn qualified_capture_columns(alias: &str) -> String {
+        CAPTURE_COLUMNS
+            .split(',')
+            .map(|column| format!("{}.{}", alias, column.trim()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Replace the renditions of a capture and point the thumbnail at the primary one.
+    /// Does nothing if the capture no longer exists.
+    pub async fn save_capture_renditions(
+        &self,
+        capture_id: &Uuid,
+        renditions: &[CaptureRendition],
+        thumbnail: Option<&CaptureRendition>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut client = self.get_client().await?;
+        let tx = client.transaction().await?;
+
+        // Lock and update the capture first, then its renditions
+        if let Some(thumbnail) = thumbnail {
+            let result = Self::update_capture_in_tx(
+                &tx,
+                capture_id,
+                None,
+                ACTOR_WORKER,
+                CaptureAction::Processed,
+                &format!(
+                    "
+            UPDATE captures SET thumbnail_url = $2, thumbnail_key = $3, updated_at = NOW()
+            WHERE id = $1
+            RETURNING {CAPTURE_COLUMNS}
+        "
+                ),
+                &[capture_id, &thumbnail.url, &thumbnail.object_key],
+            )
+            .await?;
+            if result.updated().is_none() {
+                return Ok(());
+            }
+        }
+
+        tx.execute(
+            "DELETE FROM capture_renditions WHERE capture_id = $1",
+            &[capture_id],
+        )
+        .await?;
+
+        for rendition in renditions {
+            tx.execute(
+                "
+            INSERT INTO capture_renditions (capture_id, name, format, width, height, object_key, url, size_bytes)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ",
+                &[
+                    capture_id,
+                    &rendition.name,
+                    &rendition.format,
+                    &rendition.width,
+                    &rendition.height,
+                    &rendition.object_key,
+                    &rendition.url,
+                    &rendition.size_bytes,
+                ],
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get renditions for a set of captures, grouped by capture id
+    pub async fn get_renditions_for_captures(
+        &self,
+        capture_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<CaptureRendition>>, Box<dyn std::error::Error + Send + Sync>>
+    {
+        let mut grouped: HashMap<Uuid, Vec<CaptureRendition>> = HashMap::new();
+        if capture_ids.is_empty() {
+            return Ok(grouped);
+        }
+
+        let client = self.get_client().await?;
+        let rows = client
+            .query(
+                "
+            SELECT capture_id, name, format, width, height, object_key, url, size_bytes
+            FROM capture_renditions
+            WHERE capture_id = ANY($1)
+            ORDER BY capture_id, width, format
+        ",
+                &[&capture_ids],
+            )
+            .await?;
+
+        for row in rows {
+            grouped
+                .entry(row.get(0))
+                .or_default()
+                .push(CaptureRendition {
+                    name: row.get(1),
+                    format: row.get(2),
+                    width: row.get(3),
+                    height: row.get(4),
+                    object_key: row.get(5),
+                    url: row.get(6),
+                    size_bytes: row.get(7),
+                });
+        }
+
+        Ok(grouped)
+    }
+
+    /// Every object key referenced by a capture or rendition, plus the URLs of rows
+    /// whose keys could not be backfilled (the caller resolves those with the object store)
+    pub async fn get_referenced_object_keys(
+        &self,
+    ) -> Result<(HashSet<String>, Vec<String>), Box<dyn std::error::Error + Send + Sync>> {
+        let client = self.get_client().await?;
+
+        let rows = client
+            .query(
+                "
+            SELECT image_key FROM captures WHERE image_key IS NOT NULL
+            UNION
+            SELECT thumbnail_key FROM captures WHERE thumbnail_key IS NOT NULL
+            UNION
+            SELECT object_key FROM capture_renditions
+        ",
+                &[],
+            )
+            .await?;
+        let keys = rows.iter().map(|r| r.get(0)).collect();
+
+        let rows = client
+            .query(
+                "
+            SELECT image_url FROM captures WHERE image_key IS NULL
+            UNION
+            SELECT thumbnail_url FROM captures
+            WHERE thumbnail_key IS NULL AND thumbnail_url IS NOT NULL
+        ",
+                &[],
+            )
+            .await?;
+        let legacy_urls = rows.iter().map(|r| r.get(0)).collect();
+
+        Ok((keys, legacy_urls))
+    }
+
+    /// Fill `renditions` on each capture with a single query
+    pub async fn attach_renditions(
+        &self,
+        captures: &mut [Capture],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let ids: Vec<Uuid> = captures.iter().map(|c| c.id).collect();
+        let mut grouped = self.get_renditions_for_captures(&ids).await?;
+
+        for capture in captures.iter_mut() {
+            capture.renditions = grouped.remove(&capture.id).unwrap_or_default();
+        }
+
+        Ok(())
+    }
+
     fn row_to_capture(row: &tokio_postgres::Row) -> Capture {
         Capture {
             id: row.get(0),
@@ -525,6 +2020,158 @@ impl DatabaseService {
             difficulty: row.get(18),
             verified: row.get(19),
             is_public: row.get(20),
+            image_width: row.get(21),
+            image_height: row.get(22),
+            exif_orientation: row.get(23),
+            content_hash: row.get(24),
+            blurhash: row.get(25),
+            dominant_colors: row.get(26),
+            image_key: row.get(27),
+            thumbnail_key: row.get(28),
+            deleted_at: row.get(29),
+            latitude: row.get(30),
+            longitude: row.get(31),
+            notes: row.get(32),
+            version: row.get(33),
+            user_edited_fields: row.get(34),
+            renditions: Vec::new(),
+            urls_expire_at: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_without_conditions_only_excludes_the_trash() {
+        let (where_clause, params) = DatabaseService::capture_filter(&CaptureListQuery::default());
+        assert_eq!(where_clause, "is_deleted = false");
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn filter_placeholders_are_numbered_in_order() {
+        let query = CaptureListQuery {
+            user_id: Some(Uuid::nil()),
+            category: Some("nature".to_string()),
+            verified: Some(true),
+            min_confidence: Some(0.5),
+            ..Default::default()
+        };
+        let (where_clause, params) = DatabaseService::capture_filter(&query);
+        assert_eq!(
+            where_clause,
+            "is_deleted = false AND user_id = $1 AND category = $2 \
+             AND verified = $3 AND confidence >= $4"
+        );
+        assert_eq!(params.len(), 4);
+
+        let query = CaptureListQuery {
+            difficulty: Some("hard".to_string()),
+            is_public: Some(true),
+            created_from: Some(Utc::now()),
+            created_to: Some(Utc::now()),
+            ..Default::default()
+        };
+        let (where_clause, params) = DatabaseService::capture_filter(&query);
+        assert_eq!(
+            where_clause,
+            "is_deleted = false AND difficulty = $1 AND is_public = $2 \
+             AND created_at >= $3 AND created_at < $4"
+        );
+        assert_eq!(params.len(), 4);
+    }
+
+    #[test]
+    fn tags_match_any_overlaps_and_all_contains() {
+        let mut query = CaptureListQuery {
+            user_id: Some(Uuid::nil()),
+            tags: Some("volcan, lago".to_string()),
+            ..Default::default()
+        };
+        let (where_clause, params) = DatabaseService::capture_filter(&query);
+        assert_eq!(where_clause, "is_deleted = false AND user_id = $1 AND tags && $2");
+        assert_eq!(params.len(), 2);
+
+        query.tags_match = TagMatch::All;
+        let (where_clause, _) = DatabaseService::capture_filter(&query);
+        assert_eq!(where_clause, "is_deleted = false AND user_id = $1 AND tags @> $2");
+
+        // An empty tag list adds no condition
+        query.tags = Some(" , ".to_string());
+        let (where_clause, params) = DatabaseService::capture_filter(&query);
+        assert_eq!(where_clause, "is_deleted = false AND user_id = $1");
+        assert_eq!(params.len(), 1);
+    }
+
+    #[test]
+    fn confidence_sorts_nulls_last() {
+        let mut query = CaptureListQuery {
+            sort: CaptureSort::Confidence,
+            order: SortOrder::Desc,
+            ..Default::default()
+        };
+        assert_eq!(
+            DatabaseService::capture_order(&query),
+            "confidence DESC NULLS LAST, id DESC"
+        );
+
+        query.order = SortOrder::Asc;
+        assert_eq!(
+            DatabaseService::capture_order(&query),
+            "confidence ASC NULLS LAST, id ASC"
+        );
+
+        query.sort = CaptureSort::CreatedAt;
+        assert_eq!(DatabaseService::capture_order(&query), "created_at ASC, id ASC");
+    }
+
+    #[test]
+    fn keyset_placeholders_continue_after_the_filter() {
+        let query = CaptureListQuery {
+            user_id: Some(Uuid::nil()),
+            ..Default::default()
+        };
+        let (_, mut params) = DatabaseService::capture_filter(&query);
+        let cursor = CaptureCursor {
+            sort: CaptureSort::CreatedAt,
+            order: SortOrder::Desc,
+            timestamp: Some(Utc::now()),
+            confidence: None,
+            id: Uuid::nil(),
+        };
+        assert_eq!(
+            DatabaseService::keyset_condition(&cursor, &mut params),
+            "(created_at, id) < ($3, $2)"
+        );
+        assert_eq!(params.len(), 3);
+    }
+
+    #[test]
+    fn confidence_keyset_keeps_nulls_at_the_end() {
+        let mut params = Vec::new();
+        let cursor = CaptureCursor {
+            sort: CaptureSort::Confidence,
+            order: SortOrder::Asc,
+            timestamp: None,
+            confidence: Some(0.8),
+            id: Uuid::nil(),
+        };
+        assert_eq!(
+            DatabaseService::keyset_condition(&cursor, &mut params),
+            "(confidence > $2 OR (confidence = $2 AND id > $1) OR confidence IS NULL)"
+        );
+
+        let mut params = Vec::new();
+        let cursor = CaptureCursor {
+            confidence: None,
+            ..cursor
+        };
+        assert_eq!(
+            DatabaseService::keyset_condition(&cursor, &mut params),
+            "(confidence IS NULL AND id > $1)"
+        );
+    }
+}
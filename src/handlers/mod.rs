@@ -1,11 +1,17 @@
-use actix_web::{web, HttpResponse, Result};
+use actix_multipart::Multipart;
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Result};
+use futures_util::StreamExt as _;
 use std::sync::Arc;
+use tokio::io::AsyncWriteExt as _;
 use uuid::Uuid;
 use validator::Validate;
 
-use crate::database::DatabaseService;
+use crate::config::{SecurityConfig, StorageConfig};
+use crate::database::{CaptureUpdate, DatabaseService};
+use crate::imaging;
 use crate::models::*;
-use crate::storage::S3Service;
+use crate::storage::{self, LocalStore, ObjectStore, ReadUrlSigner, S3Service};
+use crate::tags::TagNormalizer;
 use crate::webhooks::{self, CapturePublishedEvent, WebhookClient};
 use serde_json::Value as JsonValue;
 
@@ -21,10 +27,54 @@ pub async fn health_check() -> Result<HttpResponse> {
     )
 }
 
+/// Validate a presign request against the storage limits.
+/// Returns the error response to send when the request is rejected.
+fn reject_presign_request(
+    req: &PresignedUrlRequest,
+    storage_config: &StorageConfig,
+) -> Option<HttpResponse> {
+    if let Err(e) = req.validate() {
+        log::warn!("❌ Validation error: {:?}", e);
+        return Some(
+            HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+                "Validation error: {:?}",
+                e
+            ))),
+        );
+    }
+
+    let content_type = req.content_type.to_lowercase();
+    if !storage_config.allowed_content_types.contains(&content_type) {
+        log::warn!("❌ Rejected content type: {}", req.content_type);
+        return Some(
+            HttpResponse::UnsupportedMediaType().json(ApiResponse::<()>::error(format!(
+                "Content type '{}' is not allowed",
+                req.content_type
+            ))),
+        );
+    }
+
+    if let Some(size) = req.expected_size_bytes {
+        if size as u64 > storage_config.max_image_size_bytes as u64 {
+            log::warn!("❌ Expected size {} exceeds limit", size);
+            return Some(
+                HttpResponse::PayloadTooLarge().json(ApiResponse::<()>::error(format!(
+                    "Image exceeds the maximum size of {} bytes",
+                    storage_config.max_image_size_bytes
+                ))),
+            );
+        }
+    }
+
+    None
+}
+
 /// Generate presigned upload URL
 pub async fn generate_presigned_url(
+    http_req: HttpRequest,
     req: web::Json<PresignedUrlRequest>,
-    s3_service: web::Data<Arc<S3Service>>,
+    object_store: web::Data<Arc<dyn ObjectStore>>,
+    storage_config: web::Data<StorageConfig>,
 ) -> Result<HttpResponse> {
     log::info!(
         "📥 Received presigned URL request: filename={}, content_type={}",
@@ -32,25 +82,20 @@ pub async fn generate_presigned_url(
         req.content_type
     );
     log::info!("inicio ******** 1 - presign request start");
-    if let Err(e) = req.validate() {
-        log::warn!("❌ Validation error: {:?}", e);
-        return Ok(
-            HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
-                "Validation error: {:?}",
-                e
-            ))),
-        );
+    if let Some(response) = reject_presign_request(&req, &storage_config) {
+        return Ok(response);
     }
 
-    let object_key = S3Service::generate_object_key(&req.filename);
+    let object_key = storage::generate_object_key(request_user_id(&http_req), &req.filename);
+    let expires_in = storage_config.presigned_url_expiry_seconds;
     log::info!("🔑 Generated object key: {}", object_key);
 
-    match s3_service
-        .generate_presigned_put_url(&object_key, &req.content_type, 3600)
+    match object_store
+        .presigned_put_url(&object_key, &req.content_type, expires_in)
         .await
     {
         Ok(upload_url) => {
-            let public_url = s3_service.get_public_url(&object_key);
+            let public_url = object_store.public_url(&object_key);
 
             log::info!("✅ Presigned URL generated successfully");
             log::info!("fin ********1 - presign request end");
@@ -64,7 +109,7 @@ pub async fn generate_presigned_url(
                 upload_url,
                 object_key,
                 public_url,
-                expires_in_seconds: 3600,
+                expires_in_seconds: expires_in as i64,
             };
 
             Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
@@ -80,20 +125,238 @@ pub async fn generate_presigned_url(
     }
 }
 
-/// Create a new capture
-pub async fn create_capture(
-    req: web::Json<CreateCaptureRequest>,
-    db_service: web::Data<Arc<DatabaseService>>,
+/// Generate a presigned POST policy. Unlike the presigned PUT, S3 enforces the
+/// content-length range and the exact Content-Type on upload.
+pub async fn generate_presigned_post(
+    http_req: HttpRequest,
+    req: web::Json<PresignedUrlRequest>,
+    s3_service: Option<web::Data<Arc<S3Service>>>,
+    storage_config: web::Data<StorageConfig>,
 ) -> Result<HttpResponse> {
-    let mut payload = req.into_inner();
-    log::info!("📥 Received create capture request");
-    log::debug!("   Device local ID: {:?}", payload.device_local_id);
-    log::debug!("   Image URL: {}", payload.image_url);
-    log::debug!("   Has vision_result: {}", payload.vision_result.is_some());
+    let Some(s3_service) = s3_service else {
+        return Ok(s3_backend_required());
+    };
 
-    if let Err(e) = payload.validate() {
+    log::info!(
+        "📥 Received presigned POST request: filename={}, content_type={}, expected_size={:?}",
+        req.filename,
+        req.content_type,
+        req.expected_size_bytes
+    );
+    if let Some(response) = reject_presign_request(&req, &storage_config) {
+        return Ok(response);
+    }
+
+    let object_key = storage::generate_object_key(request_user_id(&http_req), &req.filename);
+    let expires_in = storage_config.presigned_url_expiry_seconds;
+    let max_size_bytes = req
+        .expected_size_bytes
+        .map(|size| size as u64)
+        .unwrap_or(storage_config.max_image_size_bytes as u64);
+
+    match s3_service.generate_presigned_post(
+        &object_key,
+        &req.content_type,
+        max_size_bytes,
+        expires_in,
+    ) {
+        Ok(post) => {
+            log::info!("✅ Presigned POST generated for {}", object_key);
+            let response = PresignedPostResponse {
+                url: post.url,
+                fields: post.fields,
+                public_url: s3_service.public_url(&object_key),
+                object_key,
+                max_size_bytes: max_size_bytes as i64,
+                expires_in_seconds: expires_in as i64,
+            };
+
+            Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            log::error!("❌ Failed to generate presigned POST: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to generate upload URL".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Upload an image through the server (multipart field `file`), under the prefix of
+/// the `X-User-Id` user. Enforces the size limit while reading, checks the content
+/// type allowlist and verifies the bytes decode as an image before anything reaches S3.
+pub async fn upload_image(
+    http_req: HttpRequest,
+    mut payload: Multipart,
+    object_store: web::Data<Arc<dyn ObjectStore>>,
+    storage_config: web::Data<StorageConfig>,
+) -> Result<HttpResponse> {
+    log::info!("📥 Received multipart upload");
+    let user_id = request_user_id(&http_req);
+
+    while let Some(field) = payload.next().await {
+        let mut field = match field {
+            Ok(f) => f,
+            Err(e) => {
+                log::warn!("❌ Invalid multipart payload: {}", e);
+                return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                    "Invalid multipart payload".to_string(),
+                )));
+            }
+        };
+
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let content_type = field
+            .content_type()
+            .map(|mime| mime.essence_str().to_lowercase())
+            .unwrap_or_default();
+        if !storage_config.allowed_content_types.contains(&content_type) {
+            log::warn!("❌ Rejected content type: {:?}", content_type);
+            return Ok(
+                HttpResponse::UnsupportedMediaType().json(ApiResponse::<()>::error(format!(
+                    "Content type '{}' is not allowed",
+                    content_type
+                ))),
+            );
+        }
+
+        let filename = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .unwrap_or("upload.jpg")
+            .to_string();
+
+        // Spool to a temporary file instead of memory, and stop reading as soon as
+        // the limit is crossed
+        let max_bytes = storage_config.max_image_size_bytes;
+        let spool = storage::SpoolFile::new();
+        let mut file = match tokio::fs::File::create(spool.path()).await {
+            Ok(f) => f,
+            Err(e) => {
+                log::error!("❌ Failed to create spool file: {}", e);
+                return Ok(
+                    HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                        "Failed to store upload".to_string(),
+                    )),
+                );
+            }
+        };
+        let mut size = 0usize;
+        while let Some(chunk) = field.next().await {
+            let chunk = match chunk {
+                Ok(c) => c,
+                Err(e) => {
+                    log::warn!("❌ Failed to read upload: {}", e);
+                    return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                        "Failed to read upload".to_string(),
+                    )));
+                }
+            };
+            if size + chunk.len() > max_bytes {
+                log::warn!("❌ Upload exceeds {} bytes", max_bytes);
+                return Ok(
+                    HttpResponse::PayloadTooLarge().json(ApiResponse::<()>::error(format!(
+                        "Image exceeds the maximum size of {} bytes",
+                        max_bytes
+                    ))),
+                );
+            }
+            size += chunk.len();
+            if let Err(e) = file.write_all(&chunk).await {
+                log::error!("❌ Failed to write spool file: {}", e);
+                return Ok(
+                    HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                        "Failed to store upload".to_string(),
+                    )),
+                );
+            }
+        }
+        if let Err(e) = file.flush().await {
+            log::error!("❌ Failed to write spool file: {}", e);
+            return Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to store upload".to_string(),
+                )),
+            );
+        }
+        drop(file);
+
+        // Decoding is CPU bound; run it on the blocking pool
+        let declared_type = content_type.clone();
+        let spool_path = spool.path().to_path_buf();
+        let limits = imaging::upload_limits(&storage_config);
+        let validation = web::block(move || {
+            imaging::validate_upload(&spool_path, &declared_type, &limits)
+        })
+        .await?;
+        let (width, height) = match validation {
+            Ok(v) => v,
+            Err(e) => {
+                log::warn!("❌ Invalid image upload: {}", e);
+                return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(e)));
+            }
+        };
+
+        let object_key = storage::generate_object_key(user_id, &filename);
+        let size_bytes = size as i64;
+        log::info!("🔑 Uploading {} ({} bytes)", object_key, size_bytes);
+
+        return match object_store
+            .put_file(&object_key, spool.path(), &content_type)
+            .await
+        {
+            Ok(public_url) => {
+                log::info!("✅ Upload stored: {}", object_key);
+                Ok(
+                    HttpResponse::Created().json(ApiResponse::success(UploadResponse {
+                        object_key,
+                        public_url,
+                        content_type,
+                        size_bytes,
+                        width,
+                        height,
+                    })),
+                )
+            }
+            Err(e) => {
+                log::error!("❌ Failed to store upload {}: {}", object_key, e);
+                Ok(
+                    HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                        "Failed to store upload".to_string(),
+                    )),
+                )
+            }
+        };
+    }
+
+    Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+        "Missing multipart field 'file'".to_string(),
+    )))
+}
+
+/// Response for S3-only endpoints when another storage backend is configured
+fn s3_backend_required() -> HttpResponse {
+    HttpResponse::NotImplemented().json(ApiResponse::<()>::error(
+        "This endpoint requires the S3 storage backend".to_string(),
+    ))
+}
+
+/// S3 allows at most 10,000 parts per multipart upload
+const MAX_MULTIPART_PARTS: u64 = 10_000;
+
+/// Validate a multipart upload reference and check the key was issued to `user_id`
+fn reject_multipart_ref(
+    upload: &MultipartUploadRef,
+    user_id: Option<Uuid>,
+) -> Option<HttpResponse> {
+    if let Err(e) = upload.validate() {
         log::warn!("❌ Validation error: {:?}", e);
-        return Ok(
+        return Some(
             HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
                 "Validation error: {:?}",
                 e
@@ -101,156 +364,1280 @@ pub async fn create_capture(
         );
     }
 
-    payload.author_name = payload
-        .author_name
-        .map(|value| value.trim().to_string())
-        .filter(|value| !value.is_empty());
-
-    if payload.user_id.is_none() {
+    if !upload
+        .object_key
+        .starts_with(&storage::user_key_prefix(user_id))
+    {
         log::warn!(
-            "⚠️ Capture created without user_id; stories will fall back to anonymous author"
+            "❌ Multipart upload {} was not issued to user {:?}",
+            upload.object_key,
+            user_id
         );
+        return Some(HttpResponse::Forbidden().json(ApiResponse::<()>::error(
+            "Upload was not issued to this user".to_string(),
+        )));
     }
 
-    if payload.author_name.is_none() {
-        if let Some(user_id) = payload.user_id {
-            log::warn!(
-                "⚠️ Capture for user {} missing author_name; default label will be used",
-                user_id
-            );
-        } else {
-            log::warn!(
-                "⚠️ Capture missing author_name and user context; default label will be used"
+    None
+}
+
+/// Start a multipart upload for large media. Returns the upload id and the part
+/// layout the client should use; part URLs are requested separately.
+pub async fn initiate_multipart_upload(
+    http_req: HttpRequest,
+    req: web::Json<InitiateMultipartRequest>,
+    s3_service: Option<web::Data<Arc<S3Service>>>,
+    storage_config: web::Data<StorageConfig>,
+) -> Result<HttpResponse> {
+    let Some(s3_service) = s3_service else {
+        return Ok(s3_backend_required());
+    };
+
+    log::info!(
+        "inicio ******** 1 - multipart initiate: {} ({} bytes)",
+        req.filename,
+        req.total_size_bytes
+    );
+
+    if let Err(e) = req.validate() {
+        log::warn!("❌ Validation error: {:?}", e);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+            "Validation error: {:?}",
+            e
+        ))));
+    }
+
+    let content_type = req.content_type.to_lowercase();
+    if !storage_config.allowed_content_types.contains(&content_type) {
+        log::warn!("❌ Rejected content type: {}", req.content_type);
+        return Ok(
+            HttpResponse::UnsupportedMediaType().json(ApiResponse::<()>::error(format!(
+                "Content type '{}' is not allowed",
+                req.content_type
+            ))),
+        );
+    }
+
+    let total_size = req.total_size_bytes as u64;
+    if total_size > storage_config.max_multipart_upload_bytes {
+        log::warn!("❌ Multipart size {} exceeds limit", total_size);
+        return Ok(
+            HttpResponse::PayloadTooLarge().json(ApiResponse::<()>::error(format!(
+                "Upload exceeds the maximum size of {} bytes",
+                storage_config.max_multipart_upload_bytes
+            ))),
+        );
+    }
+
+    // Grow the part size when the configured one would need more than 10,000 parts
+    let part_size = storage_config
+        .multipart_part_size_bytes
+        .max(total_size.div_ceil(MAX_MULTIPART_PARTS));
+    let part_count = total_size.div_ceil(part_size);
+
+    let object_key = storage::generate_object_key(request_user_id(&http_req), &req.filename);
+
+    match s3_service
+        .create_multipart_upload(&object_key, &content_type)
+        .await
+    {
+        Ok(upload_id) => {
+            log::info!(
+                "✅ Multipart upload started: {} ({} parts)",
+                object_key,
+                part_count
             );
+            Ok(
+                HttpResponse::Created().json(ApiResponse::success(InitiateMultipartResponse {
+                    upload_id,
+                    public_url: s3_service.public_url(&object_key),
+                    object_key,
+                    part_size_bytes: part_size as i64,
+                    part_count: part_count as i32,
+                })),
+            )
+        }
+        Err(e) => {
+            log::error!("❌ Failed to start multipart upload: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to start multipart upload".to_string(),
+                )),
+            )
         }
     }
+}
 
-    match db_service.create_capture(&payload).await {
-        Ok(capture) => {
-            log::info!("✅ Capture created successfully: ID={}", capture.id);
+/// Presign PUT URLs for a batch of parts. Clients may call this repeatedly,
+/// e.g. to refresh expired URLs when resuming.
+pub async fn presign_multipart_parts(
+    http_req: HttpRequest,
+    req: web::Json<PresignPartsRequest>,
+    s3_service: Option<web::Data<Arc<S3Service>>>,
+    storage_config: web::Data<StorageConfig>,
+) -> Result<HttpResponse> {
+    let Some(s3_service) = s3_service else {
+        return Ok(s3_backend_required());
+    };
 
-            // Enqueue for analysis if no vision_result provided or if vision_result is an empty JSON object
-            let should_enqueue = match &capture.vision_result {
-                None => true,
-                Some(JsonValue::Object(map)) if map.is_empty() => true,
-                _ => false,
-            };
+    if let Some(response) = reject_multipart_ref(&req.upload, request_user_id(&http_req)) {
+        return Ok(response);
+    }
+    if let Err(e) = req.validate() {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+            "Validation error: {:?}",
+            e
+        ))));
+    }
+    if let Some(invalid) = req
+        .part_numbers
+        .iter()
+        .find(|n| **n < 1 || **n as u64 > MAX_MULTIPART_PARTS)
+    {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+            "Invalid part number {}",
+            invalid
+        ))));
+    }
 
-            if should_enqueue {
-                log::info!("inicio ******** 3 - enqueue capture start: {}", capture.id);
-                log::info!("📋 Enqueueing capture for AI analysis...");
-                if let Err(e) = db_service.enqueue_analysis(&capture.id).await {
-                    log::error!("❌ Failed to enqueue analysis: {}", e);
-                } else {
-                    log::info!("✅ Capture enqueued for analysis");
-                }
-                log::info!("fin ********3 - enqueue capture end: {}", capture.id);
-            } else {
-                log::info!("ℹ️  Capture already has vision_result, skipping analysis queue");
+    let expires_in = storage_config.presigned_url_expiry_seconds;
+    let mut parts = Vec::with_capacity(req.part_numbers.len());
+    for part_number in &req.part_numbers {
+        match s3_service
+            .generate_presigned_part_url(
+                &req.upload.object_key,
+                &req.upload.upload_id,
+                *part_number,
+                expires_in,
+            )
+            .await
+        {
+            Ok(upload_url) => parts.push(PresignedPart {
+                part_number: *part_number,
+                upload_url,
+            }),
+            Err(e) => {
+                log::error!("❌ Failed to presign part {}: {}", part_number, e);
+                return Ok(
+                    HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                        "Failed to generate part upload URLs".to_string(),
+                    )),
+                );
             }
-
-            Ok(HttpResponse::Created().json(ApiResponse::success(capture)))
         }
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(PresignPartsResponse {
+        parts,
+        expires_in_seconds: expires_in as i64,
+    })))
+}
+
+/// List the parts S3 already holds so an interrupted client can resume
+pub async fn get_multipart_status(
+    http_req: HttpRequest,
+    query: web::Query<MultipartUploadRef>,
+    s3_service: Option<web::Data<Arc<S3Service>>>,
+) -> Result<HttpResponse> {
+    let Some(s3_service) = s3_service else {
+        return Ok(s3_backend_required());
+    };
+
+    if let Some(response) = reject_multipart_ref(&query, request_user_id(&http_req)) {
+        return Ok(response);
+    }
+
+    match s3_service
+        .list_uploaded_parts(&query.object_key, &query.upload_id)
+        .await
+    {
+        Ok(parts) => Ok(
+            HttpResponse::Ok().json(ApiResponse::success(MultipartStatusResponse {
+                object_key: query.object_key.clone(),
+                upload_id: query.upload_id.clone(),
+                parts: parts
+                    .into_iter()
+                    .map(|part| CompletedUploadPart {
+                        part_number: part.part_number,
+                        etag: part.etag,
+                        size_bytes: Some(part.size_bytes),
+                    })
+                    .collect(),
+            })),
+        ),
         Err(e) => {
-            log::error!("❌ Failed to create capture: {}", e);
-            Ok(
+            log::warn!("❌ Failed to list parts for {}: {}", query.object_key, e);
+            Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+                "Multipart upload not found".to_string(),
+            )))
+        }
+    }
+}
+
+/// Complete a multipart upload. The assembled object is checked against the
+/// size limit and removed if it exceeds it.
+pub async fn complete_multipart_upload(
+    http_req: HttpRequest,
+    req: web::Json<CompleteMultipartRequest>,
+    s3_service: Option<web::Data<Arc<S3Service>>>,
+    storage_config: web::Data<StorageConfig>,
+) -> Result<HttpResponse> {
+    let Some(s3_service) = s3_service else {
+        return Ok(s3_backend_required());
+    };
+
+    log::info!(
+        "inicio ******** 1 - multipart complete: {}",
+        req.upload.object_key
+    );
+
+    if let Some(response) = reject_multipart_ref(&req.upload, request_user_id(&http_req)) {
+        return Ok(response);
+    }
+    if let Err(e) = req.validate() {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+            "Validation error: {:?}",
+            e
+        ))));
+    }
+
+    // S3 requires the parts in ascending order
+    let mut parts: Vec<(i32, String)> = req
+        .parts
+        .iter()
+        .map(|part| (part.part_number, part.etag.clone()))
+        .collect();
+    parts.sort_by_key(|(part_number, _)| *part_number);
+
+    let object_key = &req.upload.object_key;
+    if let Err(e) = s3_service
+        .complete_multipart_upload(object_key, &req.upload.upload_id, &parts)
+        .await
+    {
+        log::error!("❌ Failed to complete multipart upload {}: {}", object_key, e);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+            "Failed to complete multipart upload".to_string(),
+        )));
+    }
+
+    let metadata = match s3_service.head_object(object_key).await {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => {
+            return Ok(
                 HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
-                    "Failed to create capture".to_string(),
+                    "Completed upload not found in storage".to_string(),
                 )),
             )
         }
+        Err(e) => {
+            log::error!("❌ Failed to verify completed upload {}: {}", object_key, e);
+            return Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to verify upload".to_string(),
+                )),
+            );
+        }
+    };
+
+    if metadata.size_bytes as u64 > storage_config.max_multipart_upload_bytes {
+        log::warn!(
+            "❌ Completed upload {} is {} bytes, over the limit; deleting",
+            object_key,
+            metadata.size_bytes
+        );
+        if let Err(e) = s3_service.delete_object(object_key).await {
+            log::error!("❌ Failed to delete oversized upload {}: {}", object_key, e);
+        }
+        return Ok(
+            HttpResponse::PayloadTooLarge().json(ApiResponse::<()>::error(format!(
+                "Upload exceeds the maximum size of {} bytes",
+                storage_config.max_multipart_upload_bytes
+            ))),
+        );
     }
+
+    log::info!("✅ Multipart upload completed: {}", object_key);
+    Ok(
+        HttpResponse::Ok().json(ApiResponse::success(CompleteMultipartResponse {
+            object_key: object_key.clone(),
+            public_url: s3_service.public_url(object_key),
+            content_type: metadata.content_type,
+            size_bytes: metadata.size_bytes,
+        })),
+    )
 }
 
-/// Get capture by ID
-pub async fn get_capture(
-    path: web::Path<Uuid>,
-    db_service: web::Data<Arc<DatabaseService>>,
+/// Abort a multipart upload and discard its parts
+pub async fn abort_multipart_upload(
+    http_req: HttpRequest,
+    req: web::Json<MultipartUploadRef>,
+    s3_service: Option<web::Data<Arc<S3Service>>>,
 ) -> Result<HttpResponse> {
-    let capture_id = path.into_inner();
+    let Some(s3_service) = s3_service else {
+        return Ok(s3_backend_required());
+    };
 
-    match db_service.get_capture_by_id(&capture_id).await {
-        Ok(Some(capture)) => Ok(HttpResponse::Ok().json(ApiResponse::success(capture))),
-        Ok(None) => Ok(HttpResponse::NotFound()
-            .json(ApiResponse::<()>::error("Capture not found".to_string()))),
+    if let Some(response) = reject_multipart_ref(&req, request_user_id(&http_req)) {
+        return Ok(response);
+    }
+
+    match s3_service
+        .abort_multipart_upload(&req.object_key, &req.upload_id)
+        .await
+    {
+        Ok(()) => {
+            log::info!("🗑️ Multipart upload aborted: {}", req.object_key);
+            Ok(HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+                "object_key": req.object_key,
+                "upload_id": req.upload_id,
+                "aborted": true
+            }))))
+        }
         Err(e) => {
-            log::error!("Failed to get capture: {}", e);
+            log::error!("❌ Failed to abort multipart upload {}: {}", req.object_key, e);
             Ok(
                 HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
-                    "Failed to retrieve capture".to_string(),
+                    "Failed to abort multipart upload".to_string(),
                 )),
             )
         }
     }
-}
+}
+
+/// Object confirmed to exist in our bucket under the uploader's prefix
+struct VerifiedUpload {
+    object_key: String,
+    size_bytes: i64,
+    content_type: String,
+}
+
+/// Verify that the URL in `field` points into our bucket, under a key issued to `user_id`,
+/// and that the object actually exists and is within `MAX_IMAGE_SIZE_BYTES`.
+/// Returns the error response on rejection. Legacy `captures/{timestamp}/...` keys
+/// are accepted until `LEGACY_UPLOAD_KEYS_UNTIL`.
+async fn verify_upload(
+    object_store: &dyn ObjectStore,
+    storage_config: &StorageConfig,
+    field: &str,
+    url: &str,
+    user_id: Option<Uuid>,
+) -> std::result::Result<VerifiedUpload, HttpResponse> {
+    let object_key = match object_store.object_key_from_url(url) {
+        Some(key) => key,
+        None => {
+            log::warn!("❌ {} is not in our bucket: {}", field, url);
+            return Err(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+                "{} must point to an object uploaded to this service",
+                field
+            ))));
+        }
+    };
+
+    let legacy_key_accepted = storage::is_legacy_object_key(&object_key)
+        && storage_config
+            .legacy_upload_keys_until
+            .is_none_or(|until| chrono::Utc::now().date_naive() <= until);
+    if legacy_key_accepted {
+        log::warn!("⚠️ Accepting deprecated legacy upload key {}", object_key);
+    } else if !object_key.starts_with(&storage::user_key_prefix(user_id)) {
+        log::warn!(
+            "❌ Object {} was not issued to user {:?}",
+            object_key,
+            user_id
+        );
+        return Err(HttpResponse::Forbidden().json(ApiResponse::<()>::error(
+            "Upload was not issued to this user".to_string(),
+        )));
+    }
+
+    match object_store.head_object(&object_key).await {
+        Ok(Some(metadata)) => {
+            // Multipart uploads may be larger (MAX_MULTIPART_UPLOAD_BYTES); an image
+            // capture is still bound by the image limit
+            let is_image = metadata
+                .content_type
+                .as_deref()
+                .is_none_or(|content_type| content_type.starts_with("image/"));
+            let max_bytes = storage_config.max_image_size_bytes;
+            if is_image && metadata.size_bytes as u64 > max_bytes as u64 {
+                log::warn!(
+                    "❌ Uploaded image {} is {} bytes, over {}",
+                    object_key,
+                    metadata.size_bytes,
+                    max_bytes
+                );
+                return Err(HttpResponse::PayloadTooLarge().json(ApiResponse::<()>::error(
+                    format!("Image exceeds the maximum size of {} bytes", max_bytes),
+                )));
+            }
+
+            Ok(VerifiedUpload {
+                object_key,
+                size_bytes: metadata.size_bytes,
+                content_type: metadata
+                    .content_type
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+            })
+        }
+        Ok(None) => {
+            log::warn!("❌ Uploaded object not found: {}", object_key);
+            Err(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+                "Uploaded object for {} not found in storage",
+                field
+            ))))
+        }
+        Err(e) => {
+            log::error!("❌ Failed to verify upload {}: {}", object_key, e);
+            Err(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to verify upload".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Create a new capture
+pub async fn create_capture(
+    http_req: HttpRequest,
+    req: web::Json<CreateCaptureRequest>,
+    db_service: web::Data<Arc<DatabaseService>>,
+    object_store: web::Data<Arc<dyn ObjectStore>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
+    storage_config: web::Data<StorageConfig>,
+) -> Result<HttpResponse> {
+    let mut payload = req.into_inner();
+    log::info!("📥 Received create capture request");
+    log::debug!("   Device local ID: {:?}", payload.device_local_id);
+    log::debug!("   Image URL: {}", payload.image_url);
+    log::debug!("   Has vision_result: {}", payload.vision_result.is_some());
+
+    if let Err(e) = payload.validate() {
+        log::warn!("❌ Validation error: {:?}", e);
+        return Ok(
+            HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+                "Validation error: {:?}",
+                e
+            ))),
+        );
+    }
+
+    payload.author_name = payload
+        .author_name
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    payload.user_id = request_user_id(&http_req);
+
+    if payload.user_id.is_none() {
+        log::warn!(
+            "⚠️ Capture created without user_id; stories will fall back to anonymous author"
+        );
+    }
+
+    match verify_upload(
+        object_store.get_ref().as_ref(),
+        &storage_config,
+        "image_url",
+        &payload.image_url,
+        payload.user_id,
+    )
+    .await
+    {
+        Ok(upload) => {
+            log::debug!(
+                "   Verified upload: {} bytes, {}",
+                upload.size_bytes,
+                upload.content_type
+            );
+            payload.image_size = Some(upload.size_bytes);
+            payload.storage_type = Some(upload.content_type);
+            payload.image_key = Some(upload.object_key);
+        }
+        Err(response) => return Ok(response),
+    }
+    // A client thumbnail goes through the same checks: it becomes a key the capture owns
+    // (and deletes with it), so it must be an upload issued to this user
+    if let Some(thumbnail_url) = payload.thumbnail_url.as_deref() {
+        match verify_upload(
+            object_store.get_ref().as_ref(),
+            &storage_config,
+            "thumbnail_url",
+            thumbnail_url,
+            payload.user_id,
+        )
+        .await
+        {
+            Ok(upload) => payload.thumbnail_key = Some(upload.object_key),
+            Err(response) => return Ok(response),
+        }
+    }
+
+    if payload.author_name.is_none() {
+        if let Some(user_id) = payload.user_id {
+            log::warn!(
+                "⚠️ Capture for user {} missing author_name; default label will be used",
+                user_id
+            );
+        } else {
+            log::warn!(
+                "⚠️ Capture missing author_name and user context; default label will be used"
+            );
+        }
+    }
+
+    match db_service.create_capture(&payload, &request_actor(&http_req)).await {
+        Ok(mut capture) => {
+            log::info!("✅ Capture created successfully: ID={}", capture.id);
+
+            log::info!("inicio ******** 3 - enqueue capture start: {}", capture.id);
+            enqueue_capture_jobs(&db_service, &capture).await;
+            log::info!("fin ********3 - enqueue capture end: {}", capture.id);
+
+            read_urls.resolve_capture(&mut capture).await;
+            Ok(HttpResponse::Created().json(ApiResponse::success(capture)))
+        }
+        Err(e) => {
+            log::error!("❌ Failed to create capture: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to create capture".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Enqueue the processing jobs for a new capture.
+/// Image processing (thumbnail, exif, hash) always runs; AI analysis only when
+/// no vision_result was provided (an empty JSON object counts as none).
+async fn enqueue_capture_jobs(db_service: &DatabaseService, capture: &Capture) {
+    let needs_analysis = match &capture.vision_result {
+        None => true,
+        Some(JsonValue::Object(map)) if map.is_empty() => true,
+        _ => false,
+    };
+
+    if needs_analysis {
+        log::info!("📋 Enqueueing capture for AI analysis...");
+    } else {
+        log::info!("ℹ️  Capture already has vision_result, skipping AI analysis");
+    }
+
+    let jobs = needs_analysis
+        .then_some(JobType::Analyze)
+        .into_iter()
+        .chain(JobType::IMAGE_PROCESSING);
+
+    for job_type in jobs {
+        if let Err(e) = db_service.enqueue_job(&capture.id, job_type).await {
+            log::error!(
+                "❌ Failed to enqueue {} job for {}: {}",
+                job_type.as_str(),
+                capture.id,
+                e
+            );
+        }
+    }
+}
+
+/// Actor recorded in the capture history: the `X-User-Id` header when it holds a
+/// user id, otherwise anonymous
+fn request_actor(req: &HttpRequest) -> String {
+    request_user_id(req)
+        .map(|user_id| format!("user:{}", user_id))
+        .unwrap_or_else(|| ACTOR_ANONYMOUS.to_string())
+}
+
+/// User making the request, from the `X-User-Id` header
+fn request_user_id(req: &HttpRequest) -> Option<Uuid> {
+    req.headers()
+        .get("X-User-Id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| Uuid::parse_str(value.trim()).ok())
+}
+
+/// Notes are private: only the owner of a capture gets them back
+fn hide_private_fields(captures: &mut [Capture], viewer: Option<Uuid>) {
+    for capture in captures {
+        if viewer.is_none() || capture.user_id != viewer {
+            capture.notes = None;
+        }
+    }
+}
+
+/// ETag of a capture version
+fn capture_etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Versions accepted by the `If-Match` header. `None` without the header or with
+/// `*`; an empty list (nothing matches) for weak or malformed tags.
+fn if_match_versions(req: &HttpRequest) -> Option<Vec<i32>> {
+    let value = req.headers().get(header::IF_MATCH)?.to_str().unwrap_or_default();
+    let tags: Vec<&str> = value.split(',').map(str::trim).collect();
+    if tags.contains(&"*") {
+        return None;
+    }
+
+    Some(
+        tags.iter()
+            .filter_map(|tag| tag.strip_prefix('"')?.strip_suffix('"')?.parse().ok())
+            .collect(),
+    )
+}
+
+/// 412 for an `If-Match` that no longer matches, with the current ETag
+fn precondition_failed(capture_id: &Uuid, current_version: i32) -> HttpResponse {
+    log::warn!(
+        "⚠️ Version mismatch on capture {} (current version {})",
+        capture_id,
+        current_version
+    );
+    HttpResponse::PreconditionFailed()
+        .insert_header((header::ETAG, capture_etag(current_version)))
+        .json(ApiResponse::<()>::error(format!(
+            "Capture was modified (current version {})",
+            current_version
+        )))
+}
+
+/// Get capture by ID
+pub async fn get_capture(
+    http_req: HttpRequest,
+    path: web::Path<Uuid>,
+    db_service: web::Data<Arc<DatabaseService>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
+) -> Result<HttpResponse> {
+    let capture_id = path.into_inner();
+
+    match db_service.get_capture_by_id(&capture_id).await {
+        Ok(Some(mut capture)) => {
+            if let Err(e) = db_service
+                .attach_renditions(std::slice::from_mut(&mut capture))
+                .await
+            {
+                log::warn!("Failed to load renditions for {}: {}", capture_id, e);
+            }
+            hide_private_fields(std::slice::from_mut(&mut capture), request_user_id(&http_req));
+            read_urls.resolve_capture(&mut capture).await;
+            Ok(HttpResponse::Ok()
+                .insert_header((header::ETAG, capture_etag(capture.version)))
+                .json(ApiResponse::success(capture)))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound()
+            .json(ApiResponse::<()>::error("Capture not found".to_string()))),
+        Err(e) => {
+            log::error!("Failed to get capture: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to retrieve capture".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Get captures list with filters, sort and pagination
+pub async fn list_captures(
+    http_req: HttpRequest,
+    query: web::Query<CaptureListQuery>,
+    db_service: web::Data<Arc<DatabaseService>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
+    tag_normalizer: web::Data<Arc<TagNormalizer>>,
+) -> Result<HttpResponse> {
+    if let Err(e) = query.validate() {
+        log::warn!("❌ Validation error: {:?}", e);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+            "Validation error: {:?}",
+            e
+        ))));
+    }
+
+    // Filter tags are compared against normalised tags
+    let mut query = query.into_inner();
+    if query.tags.is_some() {
+        let tags = query.tag_list();
+        query.tags = Some(
+            tag_normalizer
+                .normalize_all(tags.iter().map(String::as_str))
+                .join(","),
+        );
+    }
+
+    // A cursor only continues the listing it came from
+    let cursor = match query.cursor.as_deref() {
+        None => None,
+        Some(raw) => match CaptureCursor::decode(raw) {
+            Some(cursor) if cursor.sort == query.sort && cursor.order == query.order => {
+                Some(cursor)
+            }
+            _ => {
+                return Ok(HttpResponse::BadRequest()
+                    .json(ApiResponse::<()>::error("Invalid cursor".to_string())))
+            }
+        },
+    };
+
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let include_total = query.include_total.unwrap_or(cursor.is_none());
+
+    // One extra row tells whether there is a next page without counting
+    match db_service
+        .get_captures(
+            &query,
+            cursor.as_ref(),
+            (page - 1) * limit,
+            limit + 1,
+            include_total,
+        )
+        .await
+    {
+        Ok((mut captures, total)) => {
+            let has_more = captures.len() > limit as usize;
+            captures.truncate(limit as usize);
+            let next_cursor = if has_more {
+                captures
+                    .last()
+                    .map(|last| CaptureCursor::after(last, query.sort, query.order).encode())
+            } else {
+                None
+            };
+
+            if let Err(e) = db_service.attach_renditions(&mut captures).await {
+                log::warn!("Failed to load renditions for capture list: {}", e);
+            }
+            hide_private_fields(&mut captures, request_user_id(&http_req));
+            read_urls.resolve_captures(&mut captures).await;
+
+            let response = CaptureListResponse {
+                captures,
+                total,
+                page,
+                limit,
+                has_more,
+                next_cursor,
+            };
+
+            Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            log::error!("Failed to list captures: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to retrieve captures".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Full-text search over capture names, descriptions and tags
+pub async fn search_captures(
+    http_req: HttpRequest,
+    query: web::Query<CaptureSearchQuery>,
+    db_service: web::Data<Arc<DatabaseService>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
+) -> Result<HttpResponse> {
+    if let Err(e) = query.validate() {
+        log::warn!("❌ Validation error: {:?}", e);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+            "Validation error: {:?}",
+            e
+        ))));
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    match db_service
+        .search_captures(
+            &query.q,
+            query.user_id,
+            request_user_id(&http_req),
+            (page - 1) * limit,
+            limit + 1,
+        )
+        .await
+    {
+        Ok(rows) => {
+            let has_more = rows.len() > limit as usize;
+            let (mut captures, scores): (Vec<Capture>, Vec<(f32, String)>) = rows
+                .into_iter()
+                .take(limit as usize)
+                .map(|(capture, rank, headline)| (capture, (rank, headline)))
+                .unzip();
+
+            if let Err(e) = db_service.attach_renditions(&mut captures).await {
+                log::warn!("Failed to load renditions for search results: {}", e);
+            }
+            hide_private_fields(&mut captures, request_user_id(&http_req));
+            read_urls.resolve_captures(&mut captures).await;
+
+            let results = captures
+                .into_iter()
+                .zip(scores)
+                .map(|(capture, (rank, headline))| CaptureSearchHit {
+                    capture,
+                    rank,
+                    headline,
+                })
+                .collect();
+
+            Ok(HttpResponse::Ok().json(ApiResponse::success(CaptureSearchResponse {
+                results,
+                page,
+                limit,
+                has_more,
+            })))
+        }
+        Err(e) => {
+            log::error!("Failed to search captures: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to search captures".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Shape a geo query result: split off the extra row, resolve URLs and pair each
+/// capture with its distance
+async fn geo_captures_response(
+    rows: Vec<(Capture, f64)>,
+    limit: i32,
+    viewer: Option<Uuid>,
+    db_service: &DatabaseService,
+    read_urls: &ReadUrlSigner,
+) -> GeoCapturesResponse {
+    let has_more = rows.len() > limit as usize;
+    let (mut captures, distances): (Vec<Capture>, Vec<f64>) =
+        rows.into_iter().take(limit as usize).unzip();
+
+    if let Err(e) = db_service.attach_renditions(&mut captures).await {
+        log::warn!("Failed to load renditions for geo results: {}", e);
+    }
+    hide_private_fields(&mut captures, viewer);
+    read_urls.resolve_captures(&mut captures).await;
+
+    let results = captures
+        .into_iter()
+        .zip(distances)
+        .map(|(capture, distance_m)| GeoCaptureHit {
+            capture,
+            distance_m,
+        })
+        .collect();
+
+    GeoCapturesResponse {
+        results,
+        limit,
+        has_more,
+    }
+}
+
+/// Captures within a radius of a point, nearest first
+pub async fn nearby_captures(
+    http_req: HttpRequest,
+    query: web::Query<NearbyQuery>,
+    db_service: web::Data<Arc<DatabaseService>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
+) -> Result<HttpResponse> {
+    if let Err(e) = query.validate() {
+        log::warn!("❌ Validation error: {:?}", e);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+            "Validation error: {:?}",
+            e
+        ))));
+    }
+
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+
+    match db_service
+        .get_captures_nearby(query.lat, query.lon, query.radius_m, query.user_id, limit + 1)
+        .await
+    {
+        Ok(rows) => {
+            let viewer = request_user_id(&http_req);
+            let response =
+                geo_captures_response(rows, limit, viewer, &db_service, &read_urls).await;
+            Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            log::error!("Failed to get nearby captures: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to retrieve captures".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Captures inside a bounding box, nearest to the reference point first
+pub async fn bbox_captures(
+    http_req: HttpRequest,
+    query: web::Query<BboxQuery>,
+    db_service: web::Data<Arc<DatabaseService>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
+) -> Result<HttpResponse> {
+    if let Err(e) = query.validate() {
+        log::warn!("❌ Validation error: {:?}", e);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+            "Validation error: {:?}",
+            e
+        ))));
+    }
+    if query.min_lat > query.max_lat {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+            "min_lat must not be greater than max_lat".to_string(),
+        )));
+    }
+
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+
+    match db_service.get_captures_in_bbox(&query, limit + 1).await {
+        Ok(rows) => {
+            let viewer = request_user_id(&http_req);
+            let response =
+                geo_captures_response(rows, limit, viewer, &db_service, &read_urls).await;
+            Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            log::error!("Failed to get captures in bbox: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to retrieve captures".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Captures of a map viewport grouped into grid clusters for the zoom level
+pub async fn capture_clusters(
+    query: web::Query<ClusterQuery>,
+    db_service: web::Data<Arc<DatabaseService>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
+) -> Result<HttpResponse> {
+    if let Err(e) = query.validate() {
+        log::warn!("❌ Validation error: {:?}", e);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+            "Validation error: {:?}",
+            e
+        ))));
+    }
+    let bounds = match query.bounds() {
+        Ok(bounds) => bounds,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(message)))
+        }
+    };
+
+    let limit = query.limit.unwrap_or(500).clamp(1, 2000);
+    let cell_size_deg = query.cell_size_deg();
+
+    match db_service
+        .get_capture_clusters(&bounds, cell_size_deg, limit + 1)
+        .await
+    {
+        Ok(mut clusters) => {
+            let truncated = clusters.len() > limit as usize;
+            clusters.truncate(limit as usize);
+
+            for cluster in &mut clusters {
+                if let Some(key) = &cluster.thumbnail_key {
+                    let url = read_urls
+                        .object_url(cluster.representative_is_public, key)
+                        .await;
+                    cluster.thumbnail_url = Some(url);
+                }
+            }
+
+            Ok(HttpResponse::Ok().json(ApiResponse::success(CaptureClustersResponse {
+                zoom: query.z,
+                cell_size_deg,
+                clusters,
+                truncated,
+            })))
+        }
+        Err(e) => {
+            log::error!("Failed to cluster captures: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to retrieve clusters".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Update capture
+pub async fn update_capture(
+    http_req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: web::Bytes,
+    db_service: web::Data<Arc<DatabaseService>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
+    tag_normalizer: web::Data<Arc<TagNormalizer>>,
+) -> Result<HttpResponse> {
+    let capture_id = path.into_inner();
+
+    let content_type = http_req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase());
+    if let Some(content_type) = content_type {
+        if content_type != "application/json" && content_type != "application/merge-patch+json" {
+            return Ok(HttpResponse::UnsupportedMediaType().json(ApiResponse::<()>::error(
+                "Expected application/merge-patch+json or application/json".to_string(),
+            )));
+        }
+    }
+
+    let patch: JsonValue = match serde_json::from_slice(&body) {
+        Ok(patch) => patch,
+        Err(e) => {
+            log::warn!("❌ Invalid merge patch: {}", e);
+            return Ok(HttpResponse::BadRequest()
+                .json(ApiResponse::<()>::error(format!("Invalid JSON: {}", e))));
+        }
+    };
+
+    // Type errors and validation errors are reported together, per field
+    let (mut req, mut errors) = match UpdateCaptureRequest::from_merge_patch(patch) {
+        Ok(req) => (req, FieldErrors::new()),
+        Err(errors) => (UpdateCaptureRequest::default(), errors),
+    };
+
+    if let Patch::Value(notes) = &mut req.notes {
+        *notes = notes.trim().to_string();
+    }
+    if let Patch::Value(author_name) = &mut req.author_name {
+        *author_name = author_name.trim().to_string();
+    }
+    if let Patch::Value(category) = &mut req.category {
+        *category = category.trim().to_uppercase();
+    }
+    if let Patch::Value(difficulty) = &mut req.difficulty {
+        *difficulty = difficulty.trim().to_uppercase();
+    }
+    if let Err(e) = req.validate() {
+        for (field, messages) in field_errors(&e) {
+            errors.entry(field).or_default().extend(messages);
+        }
+    }
+    if !errors.is_empty() {
+        log::warn!("❌ Validation error: {:?}", errors);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::error_with_data(
+            "Validation error".to_string(),
+            errors,
+        )));
+    }
+
+    // Same format as the analysis tags
+    if let Patch::Value(tags) = &mut req.tags {
+        *tags = tag_normalizer.normalize_all(tags.iter().map(String::as_str));
+    }
+
+    // Notes are private: only the owner can write them (the owner of a capture never changes)
+    if req.notes.is_set() {
+        let viewer = request_user_id(&http_req);
+        match db_service.get_capture_by_id(&capture_id).await {
+            Ok(Some(capture)) if capture.user_id.is_some() && capture.user_id == viewer => {}
+            Ok(Some(_)) => {
+                return Ok(HttpResponse::Forbidden().json(ApiResponse::<()>::error(
+                    "Only the owner of the capture can edit its notes".to_string(),
+                )))
+            }
+            Ok(None) => {
+                return Ok(HttpResponse::NotFound()
+                    .json(ApiResponse::<()>::error("Capture not found".to_string())))
+            }
+            Err(e) => {
+                log::error!("Failed to get capture: {}", e);
+                return Ok(
+                    HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                        "Failed to update capture".to_string(),
+                    )),
+                );
+            }
+        }
+    }
+
+    let expected_versions = if_match_versions(&http_req);
+    match db_service
+        .update_capture(
+            &capture_id,
+            &req,
+            expected_versions.as_deref(),
+            &request_actor(&http_req),
+        )
+        .await
+    {
+        Ok(CaptureUpdate::Updated(mut capture)) => {
+            hide_private_fields(std::slice::from_mut(&mut capture), request_user_id(&http_req));
+            read_urls.resolve_capture(&mut capture).await;
+            Ok(HttpResponse::Ok()
+                .insert_header((header::ETAG, capture_etag(capture.version)))
+                .json(ApiResponse::success(capture)))
+        }
+        Ok(CaptureUpdate::NotFound) => Ok(HttpResponse::NotFound()
+            .json(ApiResponse::<()>::error("Capture not found".to_string()))),
+        Ok(CaptureUpdate::VersionMismatch { current_version }) => {
+            Ok(precondition_failed(&capture_id, current_version))
+        }
+        Err(e) => {
+            log::error!("Failed to update capture: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to update capture".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Saved versions of the notes of a capture, newest first
+pub async fn capture_note_history(
+    http_req: HttpRequest,
+    path: web::Path<Uuid>,
+    query: web::Query<NoteHistoryQuery>,
+    db_service: web::Data<Arc<DatabaseService>>,
+) -> Result<HttpResponse> {
+    let capture_id = path.into_inner();
+    let viewer = request_user_id(&http_req);
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    match db_service.get_capture_by_id(&capture_id).await {
+        // Notes are private: only the owner can read their history
+        Ok(Some(capture)) if capture.user_id.is_some() && capture.user_id == viewer => {}
+        Ok(_) => {
+            return Ok(HttpResponse::NotFound()
+                .json(ApiResponse::<()>::error("Capture not found".to_string())))
+        }
+        Err(e) => {
+            log::error!("Failed to get capture: {}", e);
+            return Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to retrieve capture".to_string(),
+                )),
+            );
+        }
+    }
 
-/// Get captures list with pagination
-pub async fn list_captures(
-    query: web::Query<PaginationParams>,
-    db_service: web::Data<Arc<DatabaseService>>,
-) -> Result<HttpResponse> {
-    let page = query.page.unwrap_or(1).max(1);
-    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    match db_service
+        .get_note_revisions(&capture_id, (page as i64 - 1) * limit as i64, limit + 1)
+        .await
+    {
+        Ok(mut revisions) => {
+            let has_more = revisions.len() > limit as usize;
+            revisions.truncate(limit as usize);
 
-    match db_service.get_captures(None, page, limit).await {
-        Ok((captures, total)) => {
-            let response = CaptureListResponse {
-                captures,
-                total,
+            Ok(HttpResponse::Ok().json(ApiResponse::success(NoteHistoryResponse {
+                revisions,
                 page,
                 limit,
-                has_more: (page * limit) < total as i32,
-            };
-
-            Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+                has_more,
+            })))
         }
         Err(e) => {
-            log::error!("Failed to list captures: {}", e);
+            log::error!("Failed to get note history for {}: {}", capture_id, e);
             Ok(
                 HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
-                    "Failed to retrieve captures".to_string(),
+                    "Failed to retrieve note history".to_string(),
                 )),
             )
         }
     }
 }
 
-/// Update capture
-pub async fn update_capture(
+/// Who changed what in a capture, newest first. Kept after a hard delete.
+pub async fn capture_history(
     path: web::Path<Uuid>,
-    req: web::Json<UpdateCaptureRequest>,
+    query: web::Query<CaptureHistoryQuery>,
     db_service: web::Data<Arc<DatabaseService>>,
 ) -> Result<HttpResponse> {
     let capture_id = path.into_inner();
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
 
-    match db_service.update_capture(&capture_id, &req).await {
-        Ok(Some(capture)) => Ok(HttpResponse::Ok().json(ApiResponse::success(capture))),
-        Ok(None) => Ok(HttpResponse::NotFound()
-            .json(ApiResponse::<()>::error("Capture not found".to_string()))),
+    match db_service
+        .get_capture_events(&capture_id, (page as i64 - 1) * limit as i64, limit + 1)
+        .await
+    {
+        Ok(mut events) => {
+            let has_more = events.len() > limit as usize;
+            events.truncate(limit as usize);
+
+            Ok(HttpResponse::Ok().json(ApiResponse::success(CaptureHistoryResponse {
+                events,
+                page,
+                limit,
+                has_more,
+            })))
+        }
         Err(e) => {
-            log::error!("Failed to update capture: {}", e);
+            log::error!("Failed to get history for {}: {}", capture_id, e);
             Ok(
                 HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
-                    "Failed to update capture".to_string(),
+                    "Failed to retrieve capture history".to_string(),
                 )),
             )
         }
     }
 }
 
-/// Delete capture (hard delete + S3 cleanup)
+/// Delete capture. Moves it to the trash by default; `?permanent=true` removes the
+/// row and queues its storage objects for deletion (also for captures in the trash).
 pub async fn delete_capture(
+    http_req: HttpRequest,
     path: web::Path<Uuid>,
+    query: web::Query<DeleteCaptureQuery>,
     db_service: web::Data<Arc<DatabaseService>>,
-    s3_service: web::Data<Arc<S3Service>>,
+    object_store: web::Data<Arc<dyn ObjectStore>>,
 ) -> Result<HttpResponse> {
     let capture_id = path.into_inner();
-    log::info!("🗑️ Deleting capture: {}", capture_id);
+    // Only the owner can delete; anyone else gets the same 404 as a missing capture
+    let Some(owner) = request_user_id(&http_req) else {
+        return Ok(HttpResponse::NotFound()
+            .json(ApiResponse::<()>::error("Capture not found".to_string())));
+    };
 
-    // 1. Get capture to find image URL and thumbnail URL
-    let capture = match db_service.get_capture_by_id(&capture_id).await {
-        Ok(Some(c)) => c,
-        Ok(None) => {
+    if !query.permanent {
+        log::info!("🗑️ Moving capture to trash: {}", capture_id);
+        return match db_service
+            .delete_capture(&capture_id, &owner, &request_actor(&http_req))
+            .await
+        {
+            Ok(true) => Ok(
+                HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+                    "message": "Capture moved to trash"
+                }))),
+            ),
+            Ok(false) => Ok(HttpResponse::NotFound()
+                .json(ApiResponse::<()>::error("Capture not found".to_string()))),
+            Err(e) => {
+                log::error!("Failed to soft delete capture: {}", e);
+                Ok(
+                    HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                        "Failed to delete capture".to_string(),
+                    )),
+                )
+            }
+        };
+    }
+
+    log::info!("🗑️ Deleting capture permanently: {}", capture_id);
+
+    // 1. Get capture (active or in the trash) to find its storage keys
+    let capture = match db_service.get_capture_including_deleted(&capture_id).await {
+        Ok(Some(c)) if c.user_id == Some(owner) => c,
+        Ok(_) => {
             return Ok(HttpResponse::NotFound()
                 .json(ApiResponse::<()>::error("Capture not found".to_string())))
         }
@@ -267,37 +1654,17 @@ pub async fn delete_capture(
     log::info!("📸 Capture image_url: {}", capture.image_url);
     log::info!("🖼️ Capture thumbnail_url: {:?}", capture.thumbnail_url);
 
-    // 2. Delete main image from S3
-    if let Some(object_key) = extract_key_from_url(&capture.image_url) {
-        log::info!("🗑️ Deleting S3 main image: {}", object_key);
-        if let Err(e) = s3_service.delete_object(&object_key).await {
-            log::error!("❌ Failed to delete S3 object {}: {}", object_key, e);
-            // Continue to delete from DB even if S3 fails
-        } else {
-            log::info!("✅ S3 main image deleted: {}", object_key);
-        }
-    } else {
-        log::warn!(
-            "⚠️ Could not extract S3 key from URL: {}",
-            capture.image_url
-        );
-    }
-
-    // 3. Delete thumbnail from S3 (if exists)
-    if let Some(thumbnail_url) = &capture.thumbnail_url {
-        if let Some(thumbnail_key) = extract_key_from_url(thumbnail_url) {
-            log::info!("🗑️ Deleting S3 thumbnail: {}", thumbnail_key);
-            if let Err(e) = s3_service.delete_object(&thumbnail_key).await {
-                log::error!("❌ Failed to delete S3 thumbnail {}: {}", thumbnail_key, e);
-                // Continue even if thumbnail deletion fails
-            } else {
-                log::info!("✅ S3 thumbnail deleted: {}", thumbnail_key);
-            }
-        }
-    }
+    // 2. Storage objects go to the deletion outbox in the same transaction as the
+    // row delete; the storage deletion worker removes them with retries
+    let (image_key, thumbnail_key) =
+        storage::capture_object_keys(object_store.get_ref().as_ref(), &capture);
+    let object_keys: Vec<String> = image_key.into_iter().chain(thumbnail_key).collect();
 
-    // 4. Hard delete from DB
-    match db_service.hard_delete_capture(&capture_id).await {
+    // 3. Hard delete from DB
+    match db_service
+        .hard_delete_capture(&capture_id, &object_keys, &request_actor(&http_req))
+        .await
+    {
         Ok(true) => {
             log::info!("✅ Capture deleted from DB: {}", capture_id);
             Ok(
@@ -320,30 +1687,105 @@ pub async fn delete_capture(
     }
 }
 
-fn extract_key_from_url(url: &str) -> Option<String> {
-    // Simple extraction: assume key starts after the domain
-    // Example: https://crazytrip-captures.s3.amazonaws.com/captures/123/abc.jpg
-    // Key: captures/123/abc.jpg
+/// Restore a capture from the trash
+pub async fn restore_capture(
+    http_req: HttpRequest,
+    path: web::Path<Uuid>,
+    db_service: web::Data<Arc<DatabaseService>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
+) -> Result<HttpResponse> {
+    let capture_id = path.into_inner();
+    log::info!("♻️ Restoring capture: {}", capture_id);
+
+    let Some(owner) = request_user_id(&http_req) else {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            "Capture not found in trash".to_string(),
+        )));
+    };
 
-    if let Some(start_idx) = url.find(".com/") {
-        Some(url[start_idx + 5..].to_string())
-    } else if let Some(start_idx) = url.find("/captures/") {
-        // Fallback if domain format is different but path is standard
-        Some(url[start_idx + 1..].to_string())
-    } else {
-        // Maybe it IS the key?
-        if !url.starts_with("http") {
-            Some(url.to_string())
-        } else {
-            None
+    match db_service
+        .restore_capture(&capture_id, &owner, &request_actor(&http_req))
+        .await
+    {
+        Ok(Some(mut capture)) => {
+            if let Err(e) = db_service
+                .attach_renditions(std::slice::from_mut(&mut capture))
+                .await
+            {
+                log::warn!("Failed to load renditions for {}: {}", capture_id, e);
+            }
+            read_urls.resolve_capture(&mut capture).await;
+            Ok(HttpResponse::Ok().json(ApiResponse::success(capture)))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            "Capture not found in trash".to_string(),
+        ))),
+        Err(e) => {
+            log::error!("Failed to restore capture: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to restore capture".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// List the requester's trashed captures
+pub async fn list_trash(
+    http_req: HttpRequest,
+    query: web::Query<TrashQuery>,
+    db_service: web::Data<Arc<DatabaseService>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
+) -> Result<HttpResponse> {
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    let Some(owner) = request_user_id(&http_req) else {
+        return Ok(HttpResponse::Unauthorized().json(ApiResponse::<()>::error(
+            "X-User-Id header is required".to_string(),
+        )));
+    };
+
+    match db_service
+        .get_trashed_captures(&owner, page, limit)
+        .await
+    {
+        Ok((mut captures, total)) => {
+            if let Err(e) = db_service.attach_renditions(&mut captures).await {
+                log::warn!("Failed to load renditions for trash list: {}", e);
+            }
+            read_urls.resolve_captures(&mut captures).await;
+
+            let response = CaptureListResponse {
+                captures,
+                total: Some(total),
+                page,
+                limit,
+                has_more: (page as i64) * (limit as i64) < total,
+                next_cursor: None,
+            };
+
+            Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            log::error!("Failed to list trash: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to retrieve trash".to_string(),
+                )),
+            )
         }
     }
 }
 
 /// Sync upload from device
 pub async fn sync_upload(
+    http_req: HttpRequest,
     req: web::Json<SyncUploadRequest>,
     db_service: web::Data<Arc<DatabaseService>>,
+    object_store: web::Data<Arc<dyn ObjectStore>>,
+    storage_config: web::Data<StorageConfig>,
 ) -> Result<HttpResponse> {
     if let Err(e) = req.validate() {
         return Ok(
@@ -354,17 +1796,38 @@ pub async fn sync_upload(
         );
     }
 
+    let user_id = request_user_id(&http_req);
+    let actor = request_actor(&http_req);
     let mut synced = Vec::new();
     let mut failed = Vec::new();
 
     for capture_data in &req.captures {
+        let upload = match verify_upload(
+            object_store.get_ref().as_ref(),
+            &storage_config,
+            "image_url",
+            &capture_data.image_url,
+            user_id,
+        )
+        .await
+        {
+            Ok(upload) => upload,
+            Err(_) => {
+                failed.push(SyncFailure {
+                    device_local_id: capture_data.device_local_id.clone(),
+                    error: "Image upload could not be verified".to_string(),
+                });
+                continue;
+            }
+        };
+
         let create_req = CreateCaptureRequest {
-            user_id: None,
+            user_id,
             author_name: None,
             device_local_id: Some(capture_data.device_local_id.clone()),
             image_url: capture_data.image_url.clone(),
             thumbnail_url: None,
-            image_size: None,
+            image_size: Some(upload.size_bytes),
             vision_result: capture_data.vision_result.clone(),
             category: capture_data.category.clone(),
             confidence: capture_data.confidence,
@@ -372,26 +1835,24 @@ pub async fn sync_upload(
             location: capture_data.location.clone(),
             location_info: capture_data.location_info.clone(),
             orientation: capture_data.orientation.clone(),
+            storage_type: Some(upload.content_type),
+            image_key: Some(upload.object_key),
+            thumbnail_key: None,
         };
 
-        match db_service.create_capture(&create_req).await {
+        match db_service.create_capture(&create_req, &actor).await {
             Ok(capture) => {
+                enqueue_capture_jobs(&db_service, &capture).await;
+
                 synced.push(SyncedCapture {
                     device_local_id: capture_data.device_local_id.clone(),
                     server_id: capture.id,
-                    image_url: capture.image_url,
+                    image_url: capture
+                        .image_key
+                        .as_deref()
+                        .map(|key| object_store.public_url(key))
+                        .unwrap_or(capture.image_url),
                 });
-
-                // Enqueue for analysis if needed (treat empty JSON object as none)
-                let should_enqueue = match &capture.vision_result {
-                    None => true,
-                    Some(JsonValue::Object(map)) if map.is_empty() => true,
-                    _ => false,
-                };
-
-                if should_enqueue {
-                    let _ = db_service.enqueue_analysis(&capture.id).await;
-                }
             }
             Err(e) => {
                 failed.push(SyncFailure {
@@ -408,25 +1869,40 @@ pub async fn sync_upload(
 
 /// Publish a capture (make it visible in public feed)
 pub async fn publish_capture(
+    http_req: HttpRequest,
     path: web::Path<Uuid>,
     db_service: web::Data<Arc<DatabaseService>>,
-    s3_service: web::Data<Arc<S3Service>>,
+    object_store: web::Data<Arc<dyn ObjectStore>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
     webhook_client: web::Data<Arc<WebhookClient>>,
     webhooks_enabled: web::Data<bool>,
 ) -> Result<HttpResponse> {
     let capture_id = path.into_inner();
     log::info!("📢 Publishing capture: {}", capture_id);
 
-    match db_service.publish_capture(&capture_id).await {
-        Ok(Some(capture)) => {
+    let expected_versions = if_match_versions(&http_req);
+    match db_service
+        .publish_capture(
+            &capture_id,
+            expected_versions.as_deref(),
+            &request_actor(&http_req),
+        )
+        .await
+    {
+        Ok(CaptureUpdate::Updated(mut capture)) => {
             log::info!("✅ Capture published successfully: {}", capture_id);
+            let (image_key, thumbnail_key) =
+                storage::capture_object_keys(object_store.get_ref().as_ref(), &capture);
 
             // Send webhook if enabled
             if *webhooks_enabled.as_ref() {
-                let mut public_image_url = capture.image_url.clone();
-                if let Some(object_key) = extract_key_from_url(&capture.image_url) {
-                    match s3_service
-                        .generate_presigned_get_url(&object_key, 86_400)
+                let mut public_image_url = image_key
+                    .as_deref()
+                    .map(|key| object_store.public_url(key))
+                    .unwrap_or_else(|| capture.image_url.clone());
+                if let Some(object_key) = image_key {
+                    match object_store
+                        .presigned_get_url(&object_key, 86_400)
                         .await
                     {
                         Ok(url) => public_image_url = url,
@@ -438,20 +1914,21 @@ pub async fn publish_capture(
                     }
                 }
 
-                let mut public_thumbnail_url = capture.thumbnail_url.clone();
-                if let Some(thumbnail) = &capture.thumbnail_url {
-                    if let Some(thumbnail_key) = extract_key_from_url(thumbnail) {
-                        match s3_service
-                            .generate_presigned_get_url(&thumbnail_key, 86_400)
-                            .await
-                        {
-                            Ok(url) => public_thumbnail_url = Some(url),
-                            Err(e) => log::warn!(
-                                "Failed to generate presigned thumbnail URL for {}: {}",
-                                thumbnail_key,
-                                e
-                            ),
-                        }
+                let mut public_thumbnail_url = thumbnail_key
+                    .as_deref()
+                    .map(|key| object_store.public_url(key))
+                    .or_else(|| capture.thumbnail_url.clone());
+                if let Some(thumbnail_key) = thumbnail_key {
+                    match object_store
+                        .presigned_get_url(&thumbnail_key, 86_400)
+                        .await
+                    {
+                        Ok(url) => public_thumbnail_url = Some(url),
+                        Err(e) => log::warn!(
+                            "Failed to generate presigned thumbnail URL for {}: {}",
+                            thumbnail_key,
+                            e
+                        ),
                     }
                 }
 
@@ -468,6 +1945,8 @@ pub async fn publish_capture(
                     author_name: Some(author_name.clone()),
                     image_url: public_image_url,
                     thumbnail_url: public_thumbnail_url,
+                    blurhash: capture.blurhash.clone(),
+                    dominant_colors: capture.dominant_colors.clone(),
                     category: capture.category.clone(),
                     tags: capture.tags.clone(),
                     location: capture
@@ -487,13 +1966,20 @@ pub async fn publish_capture(
                 });
             }
 
-            Ok(HttpResponse::Ok().json(ApiResponse::success(capture)))
+            hide_private_fields(std::slice::from_mut(&mut capture), request_user_id(&http_req));
+            read_urls.resolve_capture(&mut capture).await;
+            Ok(HttpResponse::Ok()
+                .insert_header((header::ETAG, capture_etag(capture.version)))
+                .json(ApiResponse::success(capture)))
         }
-        Ok(None) => {
+        Ok(CaptureUpdate::NotFound) => {
             log::warn!("❌ Capture not found: {}", capture_id);
             Ok(HttpResponse::NotFound()
                 .json(ApiResponse::<()>::error("Capture not found".to_string())))
         }
+        Ok(CaptureUpdate::VersionMismatch { current_version }) => {
+            Ok(precondition_failed(&capture_id, current_version))
+        }
         Err(e) => {
             log::error!("❌ Failed to publish capture: {}", e);
             Ok(
@@ -507,17 +1993,29 @@ pub async fn publish_capture(
 
 /// Unpublish a capture (remove from public feed)
 pub async fn unpublish_capture(
+    http_req: HttpRequest,
     path: web::Path<Uuid>,
     db_service: web::Data<Arc<DatabaseService>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
     webhook_client: web::Data<Arc<WebhookClient>>,
     webhooks_enabled: web::Data<bool>,
 ) -> Result<HttpResponse> {
     let capture_id = path.into_inner();
     log::info!("🔇 Unpublishing capture: {}", capture_id);
 
-    match db_service.unpublish_capture(&capture_id).await {
-        Ok(Some(capture)) => {
+    let expected_versions = if_match_versions(&http_req);
+    match db_service
+        .unpublish_capture(
+            &capture_id,
+            expected_versions.as_deref(),
+            &request_actor(&http_req),
+        )
+        .await
+    {
+        Ok(CaptureUpdate::Updated(mut capture)) => {
             log::info!("✅ Capture unpublished successfully: {}", capture_id);
+            hide_private_fields(std::slice::from_mut(&mut capture), request_user_id(&http_req));
+            read_urls.resolve_capture(&mut capture).await;
 
             // Send webhook if enabled
             if *webhooks_enabled.as_ref() {
@@ -534,6 +2032,8 @@ pub async fn unpublish_capture(
                     author_name: Some(author_name.clone()),
                     image_url: capture.image_url.clone(),
                     thumbnail_url: capture.thumbnail_url.clone(),
+                    blurhash: capture.blurhash.clone(),
+                    dominant_colors: capture.dominant_colors.clone(),
                     category: capture.category.clone(),
                     tags: capture.tags.clone(),
                     location: capture
@@ -553,13 +2053,18 @@ pub async fn unpublish_capture(
                 });
             }
 
-            Ok(HttpResponse::Ok().json(ApiResponse::success(capture)))
+            Ok(HttpResponse::Ok()
+                .insert_header((header::ETAG, capture_etag(capture.version)))
+                .json(ApiResponse::success(capture)))
         }
-        Ok(None) => {
+        Ok(CaptureUpdate::NotFound) => {
             log::warn!("❌ Capture not found: {}", capture_id);
             Ok(HttpResponse::NotFound()
                 .json(ApiResponse::<()>::error("Capture not found".to_string())))
         }
+        Ok(CaptureUpdate::VersionMismatch { current_version }) => {
+            Ok(precondition_failed(&capture_id, current_version))
+        }
         Err(e) => {
             log::error!("❌ Failed to unpublish capture: {}", e);
             Ok(
@@ -570,3 +2075,303 @@ pub async fn unpublish_capture(
         }
     }
 }
+
+/// Serve a file from the local storage backend (signed URLs only)
+pub async fn serve_local_file(
+    path: web::Path<String>,
+    query: web::Query<SignedFileQuery>,
+    local_store: web::Data<Arc<LocalStore>>,
+) -> Result<HttpResponse> {
+    let object_key = path.into_inner();
+    if !local_store.verify_signature("GET", &object_key, query.expires, &query.signature) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()>::error(
+            "Invalid or expired signature".to_string(),
+        )));
+    }
+
+    match local_store.get_object(&object_key).await {
+        Ok(data) => Ok(HttpResponse::Ok()
+            .content_type(storage::content_type_for_key(&object_key))
+            .body(data)),
+        Err(e) => {
+            log::warn!("❌ Local file not found {}: {}", object_key, e);
+            Ok(HttpResponse::NotFound()
+                .json(ApiResponse::<()>::error("File not found".to_string())))
+        }
+    }
+}
+
+/// Receive a presigned PUT for the local storage backend
+pub async fn put_local_file(
+    path: web::Path<String>,
+    query: web::Query<SignedFileQuery>,
+    body: web::Bytes,
+    local_store: web::Data<Arc<LocalStore>>,
+) -> Result<HttpResponse> {
+    let object_key = path.into_inner();
+    if !local_store.verify_signature("PUT", &object_key, query.expires, &query.signature) {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()>::error(
+            "Invalid or expired signature".to_string(),
+        )));
+    }
+
+    let content_type = storage::content_type_for_key(&object_key);
+    match local_store
+        .put_object(&object_key, body.to_vec(), content_type)
+        .await
+    {
+        Ok(_) => Ok(HttpResponse::Ok().finish()),
+        Err(e) => {
+            log::error!("❌ Failed to store local file {}: {}", object_key, e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to store file".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// List tags with usage counts; `prefix` turns it into autocomplete
+pub async fn list_tags(
+    query: web::Query<TagListQuery>,
+    db_service: web::Data<Arc<DatabaseService>>,
+    tag_normalizer: web::Data<Arc<TagNormalizer>>,
+) -> Result<HttpResponse> {
+    if let Err(e) = query.validate() {
+        log::warn!("❌ Validation error: {:?}", e);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+            "Validation error: {:?}",
+            e
+        ))));
+    }
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let prefix = query
+        .prefix
+        .as_deref()
+        .map(|p| tag_normalizer.normalize_prefix(p));
+
+    match db_service.get_tag_counts(prefix.as_deref(), limit).await {
+        Ok(tags) => Ok(HttpResponse::Ok().json(ApiResponse::success(tags))),
+        Err(e) => {
+            log::error!("Failed to list tags: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to retrieve tags".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Most used tags over a recent time window
+pub async fn trending_tags(
+    query: web::Query<TrendingTagsQuery>,
+    db_service: web::Data<Arc<DatabaseService>>,
+) -> Result<HttpResponse> {
+    if let Err(e) = query.validate() {
+        log::warn!("❌ Validation error: {:?}", e);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+            "Validation error: {:?}",
+            e
+        ))));
+    }
+
+    let days = query.days.unwrap_or(7);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    match db_service.get_trending_tags(days, limit).await {
+        Ok(tags) => Ok(HttpResponse::Ok().json(ApiResponse::success(tags))),
+        Err(e) => {
+            log::error!("Failed to get trending tags: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to retrieve trending tags".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Captures with a given tag, newest first
+pub async fn tag_captures(
+    http_req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<TagCapturesQuery>,
+    db_service: web::Data<Arc<DatabaseService>>,
+    read_urls: web::Data<Arc<ReadUrlSigner>>,
+    tag_normalizer: web::Data<Arc<TagNormalizer>>,
+) -> Result<HttpResponse> {
+    let raw_name = path.into_inner();
+    let tag_name = tag_normalizer.normalize(&raw_name).unwrap_or(raw_name);
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    match db_service.get_captures_by_tag(&tag_name, page, limit).await {
+        Ok((mut captures, total)) => {
+            if let Err(e) = db_service.attach_renditions(&mut captures).await {
+                log::warn!("Failed to load renditions for tag {}: {}", tag_name, e);
+            }
+            hide_private_fields(&mut captures, request_user_id(&http_req));
+            read_urls.resolve_captures(&mut captures).await;
+
+            let response = CaptureListResponse {
+                captures,
+                total: Some(total),
+                page,
+                limit,
+                has_more: (page as i64) * (limit as i64) < total,
+                next_cursor: None,
+            };
+
+            Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            log::error!("Failed to get captures for tag {}: {}", tag_name, e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to retrieve captures".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Check the admin bearer token. Admin endpoints are disabled without ADMIN_API_TOKEN.
+fn reject_non_admin(req: &HttpRequest, security: &SecurityConfig) -> Option<HttpResponse> {
+    let Some(expected) = security.admin_api_token.as_deref() else {
+        return Some(HttpResponse::Forbidden().json(ApiResponse::<()>::error(
+            "Admin API is disabled".to_string(),
+        )));
+    };
+
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    // Constant-time comparison
+    let matches = provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0;
+
+    if matches {
+        None
+    } else {
+        Some(HttpResponse::Unauthorized().json(ApiResponse::<()>::error(
+            "Invalid admin token".to_string(),
+        )))
+    }
+}
+
+/// Merge tags into one (admin)
+pub async fn merge_tags(
+    http_req: HttpRequest,
+    req: web::Json<MergeTagsRequest>,
+    db_service: web::Data<Arc<DatabaseService>>,
+    security: web::Data<SecurityConfig>,
+    tag_normalizer: web::Data<Arc<TagNormalizer>>,
+) -> Result<HttpResponse> {
+    if let Some(response) = reject_non_admin(&http_req, &security) {
+        return Ok(response);
+    }
+    if let Err(e) = req.validate() {
+        log::warn!("❌ Validation error: {:?}", e);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+            "Validation error: {:?}",
+            e
+        ))));
+    }
+
+    let Some(target) = tag_normalizer.normalize(&req.target) else {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+            "Target tag is empty after normalisation".to_string(),
+        )));
+    };
+
+    match db_service.merge_tags(&req.sources, &target, ACTOR_ADMIN).await {
+        Ok((merged, affected_captures)) => {
+            log::info!(
+                "🏷️ Merged tags {:?} into '{}' ({} captures)",
+                merged,
+                target,
+                affected_captures
+            );
+            Ok(HttpResponse::Ok().json(ApiResponse::success(MergeTagsResponse {
+                target,
+                merged,
+                affected_captures,
+            })))
+        }
+        Err(e) => {
+            log::error!("Failed to merge tags: {}", e);
+            Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to merge tags".to_string(),
+                )),
+            )
+        }
+    }
+}
+
+/// Merge every existing tag into its normalised form (admin). Cleans up tags
+/// stored before normalisation or after the synonym map changed.
+pub async fn normalize_tags(
+    http_req: HttpRequest,
+    db_service: web::Data<Arc<DatabaseService>>,
+    security: web::Data<SecurityConfig>,
+    tag_normalizer: web::Data<Arc<TagNormalizer>>,
+) -> Result<HttpResponse> {
+    if let Some(response) = reject_non_admin(&http_req, &security) {
+        return Ok(response);
+    }
+
+    let names = match db_service.get_all_tags().await {
+        Ok(names) => names,
+        Err(e) => {
+            log::error!("Failed to load tags: {}", e);
+            return Ok(
+                HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                    "Failed to load tags".to_string(),
+                )),
+            );
+        }
+    };
+
+    let mut groups: std::collections::BTreeMap<String, Vec<String>> = Default::default();
+    for name in names {
+        if let Some(target) = tag_normalizer.normalize(&name) {
+            if target != name {
+                groups.entry(target).or_default().push(name);
+            }
+        }
+    }
+
+    let mut merges = Vec::with_capacity(groups.len());
+    for (target, sources) in groups {
+        match db_service.merge_tags(&sources, &target, ACTOR_ADMIN).await {
+            Ok((merged, affected_captures)) => merges.push(MergeTagsResponse {
+                target,
+                merged,
+                affected_captures,
+            }),
+            Err(e) => {
+                log::error!("Failed to merge tags {:?} into '{}': {}", sources, target, e);
+                return Ok(
+                    HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+                        "Failed to normalise tags".to_string(),
+                    )),
+                );
+            }
+        }
+    }
+
+    log::info!("🏷️ Normalised tags: {} merges", merges.len());
+    Ok(HttpResponse::Ok().json(ApiResponse::success(merges)))
+}
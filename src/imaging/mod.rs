@@ -0,0 +1,413 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::Path;
+
+use crate::config::{RenditionFormat, RenditionSpec, ResizeMode, StorageConfig};
+
+/// Rendition ya redimensionada y codificada, lista para subir
+pub struct EncodedRendition {
+    pub name: String,
+    pub format: RenditionFormat,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: Vec<u8>,
+}
+
+/// Metadata read from the image header and EXIF block
+pub struct ImageMetadata {
+    /// Width after applying the EXIF orientation
+    pub width: u32,
+    /// Height after applying the EXIF orientation
+    pub height: u32,
+    /// Raw EXIF orientation tag (1-8), if the image carries one
+    pub exif_orientation: Option<u8>,
+}
+
+/// Limits for decoding untrusted uploads
+pub fn upload_limits(config: &StorageConfig) -> Limits {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(config.max_image_dimension);
+    limits.max_image_height = Some(config.max_image_dimension);
+    limits.max_alloc = Some(config.max_image_decode_bytes);
+    limits
+}
+
+/// Check that the file at `path` really is an image of the declared content type and
+/// decodes cleanly within `limits`. Dimensions are checked from the header before any
+/// pixel data is decoded. Returns the dimensions.
+pub fn validate_upload(
+    path: &Path,
+    declared_content_type: &str,
+    limits: &Limits,
+) -> Result<(u32, u32), String> {
+    let open = || {
+        let file = File::open(path).map_err(|e| format!("Upload could not be read: {}", e))?;
+        ImageReader::new(BufReader::new(file))
+            .with_guessed_format()
+            .map_err(|e| format!("Upload could not be read: {}", e))
+    };
+
+    let reader = open()?;
+    let format = reader
+        .format()
+        .ok_or_else(|| "File is not a recognised image format".to_string())?;
+
+    if format.to_mime_type() != declared_content_type {
+        return Err(format!(
+            "Declared content type {} does not match detected {}",
+            declared_content_type,
+            format.to_mime_type()
+        ));
+    }
+
+    let (width, height) = reader
+        .into_dimensions()
+        .map_err(|e| format!("Image header could not be read: {}", e))?;
+    let too_wide = limits.max_image_width.is_some_and(|max| width > max);
+    let too_tall = limits.max_image_height.is_some_and(|max| height > max);
+    if too_wide || too_tall {
+        return Err(format!(
+            "Image dimensions {}x{} exceed the maximum of {}x{}",
+            width,
+            height,
+            limits.max_image_width.unwrap_or(u32::MAX),
+            limits.max_image_height.unwrap_or(u32::MAX)
+        ));
+    }
+
+    let mut reader = open()?;
+    reader.limits(limits.clone());
+    reader
+        .decode()
+        .map_err(|e| format!("Image could not be decoded: {}", e))?;
+
+    Ok((width, height))
+}
+
+/// Decode an image and rotate/flip it according to its EXIF orientation
+pub fn decode_oriented(image_bytes: &[u8]) -> Result<DynamicImage, image::ImageError> {
+    let mut decoder = ImageReader::new(Cursor::new(image_bytes))
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+    Ok(img)
+}
+
+/// Read dimensions and orientation without decoding the pixel data
+pub fn read_metadata(image_bytes: &[u8]) -> Result<ImageMetadata, image::ImageError> {
+    let mut decoder = ImageReader::new(Cursor::new(image_bytes))
+        .with_guessed_format()?
+        .into_decoder()?;
+    let (width, height) = decoder.dimensions();
+    let has_exif = decoder.exif_metadata()?.is_some();
+    let orientation = decoder.orientation()?;
+
+    // Orientations 5-8 swap the axes
+    let (width, height) = if orientation.to_exif() >= 5 {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    Ok(ImageMetadata {
+        width,
+        height,
+        exif_orientation: has_exif.then(|| orientation.to_exif()),
+    })
+}
+
+/// Hex encoded SHA-256 of the original bytes
+pub fn content_hash(image_bytes: &[u8]) -> String {
+    Sha256::digest(image_bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Resize an image according to a rendition spec
+pub fn resize(img: &DynamicImage, spec: &RenditionSpec) -> DynamicImage {
+    match spec.mode {
+        ResizeMode::Fill => img.resize_to_fill(spec.width, spec.height, FilterType::Lanczos3),
+        ResizeMode::Fit => {
+            // Never upscale: small originals are re-encoded at their own size
+            if img.width() <= spec.width && img.height() <= spec.height {
+                img.clone()
+            } else {
+                img.resize(spec.width, spec.height, FilterType::Lanczos3)
+            }
+        }
+    }
+}
+
+/// Encode an image in the given format.
+/// JPEG has no alpha channel and the WebP encoder is lossless-only, so both are written as RGB8.
+pub fn encode(img: &DynamicImage, format: RenditionFormat) -> Result<Vec<u8>, image::ImageError> {
+    let rgb = DynamicImage::ImageRgb8(img.to_rgb8());
+    let mut buffer = Cursor::new(Vec::new());
+    match format {
+        RenditionFormat::Jpeg => rgb.write_to(&mut buffer, ImageFormat::Jpeg)?,
+        RenditionFormat::Webp => rgb.write_to(&mut buffer, ImageFormat::WebP)?,
+    }
+    Ok(buffer.into_inner())
+}
+
+/// Produce every configured rendition in every format
+pub fn render_all(
+    img: &DynamicImage,
+    specs: &[RenditionSpec],
+    formats: &[RenditionFormat],
+) -> Result<Vec<EncodedRendition>, image::ImageError> {
+    let mut renditions = Vec::with_capacity(specs.len() * formats.len());
+
+    for spec in specs {
+        let resized = resize(img, spec);
+        for format in formats {
+            renditions.push(EncodedRendition {
+                name: spec.name.clone(),
+                format: *format,
+                width: resized.width(),
+                height: resized.height(),
+                bytes: encode(&resized, *format)?,
+            });
+        }
+    }
+
+    Ok(renditions)
+}
+
+/// Placeholder shown by clients while the real thumbnail loads
+pub struct Placeholder {
+    pub blurhash: String,
+    /// Dominant colours as `#rrggbb`, most frequent first
+    pub dominant_colors: Vec<String>,
+}
+
+/// Compute the BlurHash and dominant colour palette of an image
+pub fn placeholder(img: &DynamicImage) -> Placeholder {
+    // Both only need a coarse view of the image
+    let small = img.thumbnail(64, 64).to_rgb8();
+    let (components_x, components_y) = if small.width() >= small.height() {
+        (4, 3)
+    } else {
+        (3, 4)
+    };
+
+    Placeholder {
+        blurhash: blurhash(&small, components_x, components_y),
+        dominant_colors: dominant_colors(&small, 5),
+    }
+}
+
+const BASE83_CHARS: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+fn encode_base83(value: u32, length: u32, out: &mut String) {
+    for i in 1..=length {
+        let digit = (value / 83u32.pow(length - i)) % 83;
+        out.push(BASE83_CHARS[digit as usize] as char);
+    }
+}
+
+fn srgb_to_linear(value: u8) -> f64 {
+    let v = value as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> u32 {
+    let v = value.clamp(0.0, 1.0);
+    if v <= 0.003_130_8 {
+        (v * 12.92 * 255.0 + 0.5) as u32
+    } else {
+        ((1.055 * v.powf(1.0 / 2.4) - 0.055) * 255.0 + 0.5) as u32
+    }
+}
+
+/// BlurHash encoder (https://blurha.sh), `components_*` must be in 1..=9
+fn blurhash(img: &image::RgbImage, components_x: u32, components_y: u32) -> String {
+    let (width, height) = img.dimensions();
+    let mut factors: Vec<[f64; 3]> = Vec::with_capacity((components_x * components_y) as usize);
+
+    for j in 0..components_y {
+        for i in 0..components_x {
+            let normalisation = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            let mut factor = [0.0f64; 3];
+            for (x, y, pixel) in img.enumerate_pixels() {
+                let basis = normalisation
+                    * (std::f64::consts::PI * i as f64 * x as f64 / width as f64).cos()
+                    * (std::f64::consts::PI * j as f64 * y as f64 / height as f64).cos();
+                for c in 0..3 {
+                    factor[c] += basis * srgb_to_linear(pixel[c]);
+                }
+            }
+            let scale = 1.0 / (width * height) as f64;
+            factors.push(factor.map(|v| v * scale));
+        }
+    }
+
+    let mut hash = String::with_capacity(6 + 2 * factors.len());
+    encode_base83((components_x - 1) + (components_y - 1) * 9, 1, &mut hash);
+
+    let (dc, ac) = factors.split_first().expect("at least one component");
+    let maximum_value = if ac.is_empty() {
+        encode_base83(0, 1, &mut hash);
+        1.0
+    } else {
+        let actual_max = ac
+            .iter()
+            .flat_map(|f| f.iter())
+            .fold(0.0f64, |max, v| max.max(v.abs()));
+        let quantised = ((actual_max * 166.0 - 0.5).floor() as i64).clamp(0, 82) as u32;
+        encode_base83(quantised, 1, &mut hash);
+        (quantised + 1) as f64 / 166.0
+    };
+
+    let dc_value =
+        (linear_to_srgb(dc[0]) << 16) + (linear_to_srgb(dc[1]) << 8) + linear_to_srgb(dc[2]);
+    encode_base83(dc_value, 4, &mut hash);
+
+    for factor in ac {
+        let quantise = |v: f64| {
+            let normalised = v / maximum_value;
+            let signed_sqrt = normalised.signum() * normalised.abs().sqrt();
+            ((signed_sqrt * 9.0 + 9.5).floor() as i64).clamp(0, 18) as u32
+        };
+        let value = quantise(factor[0]) * 19 * 19 + quantise(factor[1]) * 19 + quantise(factor[2]);
+        encode_base83(value, 2, &mut hash);
+    }
+
+    hash
+}
+
+/// Most frequent colours using a 4-bit-per-channel histogram.
+/// Buckets too close to an already picked colour are skipped so the palette stays varied.
+fn dominant_colors(img: &image::RgbImage, max_colors: usize) -> Vec<String> {
+    let mut buckets: std::collections::HashMap<u16, ([u64; 3], u64)> =
+        std::collections::HashMap::new();
+    for pixel in img.pixels() {
+        let key = ((pixel[0] as u16 >> 4) << 8) | ((pixel[1] as u16 >> 4) << 4) | (pixel[2] as u16 >> 4);
+        let entry = buckets.entry(key).or_insert(([0; 3], 0));
+        for c in 0..3 {
+            entry.0[c] += pixel[c] as u64;
+        }
+        entry.1 += 1;
+    }
+
+    let mut ranked: Vec<([u64; 3], u64)> = buckets.into_values().collect();
+    ranked.sort_by_key(|bucket| std::cmp::Reverse(bucket.1));
+
+    let mut palette: Vec<[u8; 3]> = Vec::with_capacity(max_colors);
+    for (sum, count) in ranked {
+        let color = sum.map(|v| (v / count) as u8);
+        let distinct = palette.iter().all(|chosen| {
+            let distance: i32 = (0..3)
+                .map(|c| (chosen[c] as i32 - color[c] as i32).pow(2))
+                .sum();
+            distance >= 32 * 32
+        });
+        if distinct {
+            palette.push(color);
+            if palette.len() == max_colors {
+                break;
+            }
+        }
+    }
+
+    palette
+        .iter()
+        .map(|c| format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_dimension: u32) -> Limits {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(max_dimension);
+        limits.max_image_height = Some(max_dimension);
+        limits
+    }
+
+    fn png_file(width: u32, height: u32) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("upload-test-{}.png", uuid::Uuid::new_v4()));
+        image::RgbImage::new(width, height).save(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn accepts_image_within_limits() {
+        let path = png_file(64, 32);
+        let result = validate_upload(&path, "image/png", &limits(64));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result, Ok((64, 32)));
+    }
+
+    #[test]
+    fn rejects_oversized_dimensions_from_the_header() {
+        let path = png_file(65, 10);
+        let result = validate_upload(&path, "image/png", &limits(64));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            result,
+            Err("Image dimensions 65x10 exceed the maximum of 64x64".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_content_type_and_non_images() {
+        let path = png_file(8, 8);
+        let result = validate_upload(&path, "image/jpeg", &limits(64));
+        std::fs::write(&path, b"not an image").unwrap();
+        let garbage = validate_upload(&path, "image/png", &limits(64));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.unwrap_err().contains("does not match"));
+        assert_eq!(
+            garbage,
+            Err("File is not a recognised image format".to_string())
+        );
+    }
+
+    #[test]
+    fn blurhash_matches_reference_encoder() {
+        // Reference value computed with a port of the blurha.sh TypeScript encoder
+        let gradient = image::RgbImage::from_fn(8, 6, |x, y| {
+            image::Rgb([(x * 255 / 7) as u8, (y * 255 / 5) as u8, (255 - x * 255 / 7) as u8])
+        });
+        let red = image::RgbImage::from_pixel(4, 4, image::Rgb([255, 0, 0]));
+
+        assert_eq!(blurhash(&gradient, 4, 3), "L~I5e~7jfXxvu^RqfTnTeqf7fQf7");
+        // Size flag 0, no AC components, DC 0xff0000 in four base-83 digits
+        assert_eq!(blurhash(&red, 1, 1), "00TI:j");
+    }
+
+    #[test]
+    fn dominant_colors_ranks_by_frequency() {
+        let solid = image::RgbImage::from_pixel(16, 16, image::Rgb([12, 140, 200]));
+        let mostly_white = image::RgbImage::from_fn(16, 16, |x, _| {
+            if x < 12 {
+                image::Rgb([250, 250, 250])
+            } else {
+                image::Rgb([10, 20, 30])
+            }
+        });
+
+        assert_eq!(dominant_colors(&solid, 5), vec!["#0c8cc8"]);
+        assert_eq!(
+            dominant_colors(&mostly_white, 5),
+            vec!["#fafafa", "#0a141e"]
+        );
+    }
+}
@@ -2,8 +2,10 @@ mod ai;
 mod config;
 mod database;
 mod handlers;
+mod imaging;
 mod models;
 mod storage;
+mod tags;
 mod webhooks;
 mod workers;
 
@@ -12,12 +14,16 @@ use dotenvy::dotenv;
 use std::sync::Arc;
 
 use ai::AIService;
-use config::AppConfig;
+use config::{AppConfig, StorageBackend};
 use database::DatabaseService;
 use handlers::*;
-use storage::S3Service;
+use storage::{LocalStore, ObjectStore, ReadUrlSigner, S3Service};
+use tags::TagNormalizer;
 use webhooks::WebhookClient;
-use workers::AnalysisWorker;
+use workers::{
+    AnalysisWorker, MultipartCleanupWorker, OrphanCollector, StorageDeletionWorker,
+    TrashPurgeWorker,
+};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -72,16 +78,71 @@ async fn main() -> std::io::Result<()> {
         log::info!("DB schema ensured");
     }
 
-    // Initialize S3 service
-    let s3_service = match S3Service::new(&config.storage).await {
-        Ok(s3) => Arc::new(s3),
-        Err(e) => {
-            log::error!("Failed to initialize S3 service: {}", e);
-            eprintln!("Failed to initialize S3 service: {}", e);
-            std::process::exit(1);
-        }
+    // Initialize storage backend
+    let mut s3_service: Option<Arc<S3Service>> = None;
+    let mut local_store: Option<Arc<LocalStore>> = None;
+    let object_store: Arc<dyn ObjectStore> = match config.storage.backend {
+        StorageBackend::S3 => match S3Service::new(&config.storage).await {
+            Ok(s3) => {
+                let s3 = Arc::new(s3);
+                s3_service = Some(Arc::clone(&s3));
+                s3
+            }
+            Err(e) => {
+                log::error!("Failed to initialize S3 service: {}", e);
+                eprintln!("Failed to initialize S3 service: {}", e);
+                std::process::exit(1);
+            }
+        },
+        StorageBackend::Local => match LocalStore::new(&config.storage) {
+            Ok(local) => {
+                let local = Arc::new(local);
+                local_store = Some(Arc::clone(&local));
+                local
+            }
+            Err(e) => {
+                log::error!("Failed to initialize local storage: {}", e);
+                eprintln!("Failed to initialize local storage: {}", e);
+                std::process::exit(1);
+            }
+        },
     };
 
+    // One-off orphan GC: `crazytrip-crazydex-capture gc [--dry-run]`
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("gc") {
+        let dry_run = args.iter().any(|arg| arg == "--dry-run");
+        let collector = OrphanCollector::new(
+            Arc::clone(&db_service),
+            Arc::clone(&object_store),
+            config.worker.gc_grace_period_hours,
+        );
+        match collector.run(dry_run).await {
+            Ok(report) => {
+                println!(
+                    "🧹 Orphan GC{}: scanned {}, orphaned {}, deleted {}, failed {}, reclaimed {} bytes",
+                    if dry_run { " (dry run)" } else { "" },
+                    report.scanned,
+                    report.orphaned,
+                    report.deleted,
+                    report.failed,
+                    report.reclaimed_bytes
+                );
+                std::process::exit(if report.failed > 0 { 1 } else { 0 });
+            }
+            Err(e) => {
+                eprintln!("Orphan GC failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Read URLs for API responses (signed + cached)
+    let read_urls = Arc::new(ReadUrlSigner::new(
+        Arc::clone(&object_store),
+        &config.storage,
+    ));
+
     // Initialize AI service
     let ai_service = Arc::new(AIService::new(&config.ai));
 
@@ -90,13 +151,23 @@ async fn main() -> std::io::Result<()> {
         config.webhooks.stories_service_url.clone(),
     ));
 
+    let tag_normalizer = match TagNormalizer::new(&config.tags) {
+        Ok(normalizer) => Arc::new(normalizer),
+        Err(e) => {
+            log::error!("Failed to load tag synonyms: {}", e);
+            eprintln!("Failed to load tag synonyms: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     // Spawn analysis worker if enabled
     if config.worker.analysis_enabled {
         let worker = AnalysisWorker::new(
             Arc::clone(&db_service),
-            Arc::clone(&s3_service),
+            Arc::clone(&object_store),
             Arc::clone(&ai_service),
-            config.worker.analysis_interval_seconds,
+            Arc::clone(&tag_normalizer),
+            config.worker.clone(),
         );
 
         tokio::spawn(async move {
@@ -104,6 +175,53 @@ async fn main() -> std::io::Result<()> {
         });
     }
 
+    // Spawn the storage deletion outbox worker
+    let deletion_worker = StorageDeletionWorker::new(
+        Arc::clone(&db_service),
+        Arc::clone(&object_store),
+        config.worker.storage_deletion_interval_seconds,
+    );
+    tokio::spawn(async move {
+        deletion_worker.start().await;
+    });
+
+    // Spawn the trash purge worker
+    let purge_worker = TrashPurgeWorker::new(
+        Arc::clone(&db_service),
+        Arc::clone(&object_store),
+        config.worker.trash_retention_days,
+    );
+    let purge_interval_seconds = config.worker.trash_purge_interval_seconds;
+    tokio::spawn(async move {
+        purge_worker.start(purge_interval_seconds).await;
+    });
+
+    // Spawn cleanup of abandoned multipart uploads (S3 only)
+    if let Some(s3) = &s3_service {
+        let cleanup_worker = MultipartCleanupWorker::new(
+            Arc::clone(s3),
+            config.worker.multipart_cleanup_interval_seconds,
+            config.storage.multipart_upload_ttl_hours,
+        );
+        tokio::spawn(async move {
+            cleanup_worker.start().await;
+        });
+    }
+
+    // Spawn scheduled orphan GC if enabled
+    if config.worker.gc_enabled {
+        let collector = OrphanCollector::new(
+            Arc::clone(&db_service),
+            Arc::clone(&object_store),
+            config.worker.gc_grace_period_hours,
+        );
+        let interval_seconds = config.worker.gc_interval_seconds;
+        let dry_run = config.worker.gc_dry_run;
+        tokio::spawn(async move {
+            collector.start(interval_seconds, dry_run).await;
+        });
+    }
+
     // Print access information
     println!("🚀 CrazyTrip Crazydex Capture Service started!");
     println!(
@@ -120,13 +238,26 @@ async fn main() -> std::io::Result<()> {
 
     // Create and run HTTP server
     HttpServer::new(move || {
-        App::new()
+        let app = App::new();
+        // S3-only endpoints (presigned POST, multipart) answer 501 without this
+        let app = match &s3_service {
+            Some(s3) => app.app_data(web::Data::new(Arc::clone(s3))),
+            None => app,
+        };
+        let local_store = local_store.clone();
+        let max_image_size_bytes = config.storage.max_image_size_bytes;
+
+        app
             // Shared data
             .app_data(web::Data::new(Arc::clone(&db_service)))
-            .app_data(web::Data::new(Arc::clone(&s3_service)))
+            .app_data(web::Data::new(Arc::clone(&object_store)))
+            .app_data(web::Data::new(Arc::clone(&read_urls)))
             .app_data(web::Data::new(Arc::clone(&ai_service)))
             .app_data(web::Data::new(Arc::clone(&webhook_client)))
             .app_data(web::Data::new(config.webhooks.enabled))
+            .app_data(web::Data::new(config.storage.clone()))
+            .app_data(web::Data::new(config.security.clone()))
+            .app_data(web::Data::new(Arc::clone(&tag_normalizer)))
             // Middleware
             .wrap(actix_middleware::Logger::default())
             .wrap(actix_middleware::Compress::default())
@@ -135,15 +266,47 @@ async fn main() -> std::io::Result<()> {
                     .allow_any_origin()
                     .allow_any_method()
                     .allow_any_header()
+                    // Clients read it to send If-Match on capture updates
+                    .expose_headers(["ETag"])
                     .max_age(3600),
             )
             // Routes
             .service(
                 web::scope("/api/v1")
                     .route("/health", web::get().to(health_check))
+                    .route("/uploads", web::post().to(upload_image))
                     .route("/uploads/presign", web::post().to(generate_presigned_url))
+                    .route(
+                        "/uploads/presign-post",
+                        web::post().to(generate_presigned_post),
+                    )
+                    .route(
+                        "/uploads/multipart",
+                        web::post().to(initiate_multipart_upload),
+                    )
+                    .route(
+                        "/uploads/multipart/parts",
+                        web::post().to(presign_multipart_parts),
+                    )
+                    .route(
+                        "/uploads/multipart/parts",
+                        web::get().to(get_multipart_status),
+                    )
+                    .route(
+                        "/uploads/multipart/complete",
+                        web::post().to(complete_multipart_upload),
+                    )
+                    .route(
+                        "/uploads/multipart/abort",
+                        web::post().to(abort_multipart_upload),
+                    )
                     .route("/captures", web::post().to(create_capture))
                     .route("/captures", web::get().to(list_captures))
+                    .route("/captures/trash", web::get().to(list_trash))
+                    .route("/captures/search", web::get().to(search_captures))
+                    .route("/captures/nearby", web::get().to(nearby_captures))
+                    .route("/captures/bbox", web::get().to(bbox_captures))
+                    .route("/captures/clusters", web::get().to(capture_clusters))
                     .route("/captures/{id}", web::get().to(get_capture))
                     .route("/captures/{id}", web::patch().to(update_capture))
                     .route("/captures/{id}", web::delete().to(delete_capture))
@@ -152,7 +315,30 @@ async fn main() -> std::io::Result<()> {
                         "/captures/{id}/unpublish",
                         web::patch().to(unpublish_capture),
                     )
-                    .route("/sync/upload", web::post().to(sync_upload)),
+                    .route("/captures/{id}/restore", web::post().to(restore_capture))
+                    .route("/captures/{id}/history", web::get().to(capture_history))
+                    .route(
+                        "/captures/{id}/notes/history",
+                        web::get().to(capture_note_history),
+                    )
+                    .route("/tags", web::get().to(list_tags))
+                    .route("/tags/trending", web::get().to(trending_tags))
+                    .route("/tags/{name}/captures", web::get().to(tag_captures))
+                    .route("/admin/tags/merge", web::post().to(merge_tags))
+                    .route("/admin/tags/normalize", web::post().to(normalize_tags))
+                    .route("/sync/upload", web::post().to(sync_upload))
+                    .configure(|cfg| {
+                        // Signed file route for the local storage backend
+                        if let Some(local) = local_store {
+                            cfg.service(
+                                web::resource("/files/{key:.*}")
+                                    .app_data(web::Data::new(local))
+                                    .app_data(web::PayloadConfig::new(max_image_size_bytes))
+                                    .route(web::get().to(serve_local_file))
+                                    .route(web::put().to(put_local_file)),
+                            );
+                        }
+                    }),
             )
     })
     .bind((config.server.host.clone(), config.server.port))?
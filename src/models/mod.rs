@@ -1,7 +1,9 @@
+use base64::{engine::general_purpose, Engine as _};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 /// Capture model - representa una captura de imagen con análisis AI
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,16 +24,56 @@ pub struct Capture {
     pub location_info: Option<serde_json::Value>,
     pub orientation: Option<serde_json::Value>,
     pub is_deleted: bool,
+    /// Momento en que la captura se movió a la papelera
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub difficulty: Option<String>,
     pub verified: Option<bool>,
     pub is_public: bool,
+    pub image_width: Option<i32>,
+    pub image_height: Option<i32>,
+    pub exif_orientation: Option<i16>,
+    pub content_hash: Option<String>,
+    pub blurhash: Option<String>,
+    pub dominant_colors: Option<Vec<String>>,
+    /// Notas personales del usuario
+    pub notes: Option<String>,
+    /// Versión, incrementada en cada cambio; se expone como `ETag`
+    pub version: i32,
+    /// Campos editados a mano por el usuario, que el análisis no sobrescribe
+    pub user_edited_fields: Vec<String>,
+    /// Coordenadas extraídas de `location` (columnas indexadas para el mapa)
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Keys en el storage; `image_url`/`thumbnail_url` se construyen a partir de ellas
+    pub image_key: Option<String>,
+    pub thumbnail_key: Option<String>,
+    #[serde(default)]
+    pub renditions: Vec<CaptureRendition>,
+    /// Caducidad de las URLs firmadas de la respuesta (la más próxima), si las hay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub urls_expire_at: Option<DateTime<Utc>>,
+}
+
+/// Rendition derivada (thumbnail, feed, detalle) de la imagen de una captura
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureRendition {
+    pub name: String,
+    pub format: String,
+    pub width: i32,
+    pub height: i32,
+    pub object_key: String,
+    pub url: String,
+    pub size_bytes: i64,
 }
 
 /// Request para crear una captura
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreateCaptureRequest {
+    /// Dueño de la captura: sale de la cabecera `X-User-Id`, no del body
+    #[serde(skip_deserializing)]
     pub user_id: Option<Uuid>,
     pub author_name: Option<String>,
     pub device_local_id: Option<String>,
@@ -46,24 +88,256 @@ pub struct CreateCaptureRequest {
     pub location: Option<serde_json::Value>,
     pub location_info: Option<serde_json::Value>,
     pub orientation: Option<serde_json::Value>,
+    /// Content type real del objeto, rellenado al verificar el upload
+    #[serde(skip_deserializing)]
+    pub storage_type: Option<String>,
+    /// Keys del objeto subido y del thumbnail, resueltas al verificar el upload
+    #[serde(skip_deserializing)]
+    pub image_key: Option<String>,
+    #[serde(skip_deserializing)]
+    pub thumbnail_key: Option<String>,
 }
 
-/// Request para actualizar una captura
-#[derive(Debug, Deserialize)]
+/// Longitud máxima de las notas de una captura (en caracteres)
+pub const MAX_NOTES_CHARS: usize = 5000;
+
+/// Máximo de tags por captura
+pub const MAX_CAPTURE_TAGS: usize = 30;
+
+/// Valores válidos de `difficulty`
+pub const DIFFICULTIES: &[&str] = &["EASY", "MEDIUM", "HARD", "EXPERT"];
+
+/// Errores de validación por campo: `{"campo": ["mensaje", ...]}`
+pub type FieldErrors = BTreeMap<String, Vec<String>>;
+
+/// Campo de un JSON Merge Patch (RFC 7396): ausente (no cambia), `null` (se borra)
+/// o un valor nuevo
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Patch<T> {
+    #[default]
+    Missing,
+    Null,
+    Value(T),
+}
+
+impl<T> Patch<T> {
+    /// El patch incluye el campo (con valor o `null`)
+    pub fn is_set(&self) -> bool {
+        !matches!(self, Patch::Missing)
+    }
+
+    /// Valor nuevo; `None` si el campo se borra o no viene
+    pub fn value(&self) -> Option<&T> {
+        match self {
+            Patch::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Se serializa como el valor o `null` (p. ej. en los parámetros de los errores de validación)
+impl<T: Serialize> Serialize for Patch<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Patch::Value(value) => value.serialize(serializer),
+            _ => serializer.serialize_none(),
+        }
+    }
+}
+
+/// Request para actualizar una captura: un JSON Merge Patch con los campos editables
+#[derive(Debug, Default, Validate)]
 pub struct UpdateCaptureRequest {
-    pub tags: Option<Vec<String>>,
-    pub category: Option<String>,
-    pub notes: Option<String>,
+    #[validate(custom(function = "validate_patch_tags"))]
+    pub tags: Patch<Vec<String>>,
+    #[validate(custom(function = "validate_patch_category"))]
+    pub category: Patch<String>,
+    /// Notas personales; `null` o una cadena vacía las borra
+    #[validate(custom(function = "validate_patch_notes"))]
+    pub notes: Patch<String>,
+    #[validate(custom(function = "validate_patch_author_name"))]
+    pub author_name: Patch<String>,
+    /// Corrección de la información de ubicación; se fusiona con la actual (RFC 7396)
+    #[validate(custom(function = "validate_patch_location_info"))]
+    pub location_info: Patch<serde_json::Value>,
+    /// Dificultad elegida por el usuario en lugar de la del análisis
+    #[validate(custom(function = "validate_patch_difficulty"))]
+    pub difficulty: Patch<String>,
+}
+
+impl UpdateCaptureRequest {
+    /// Lee un JSON Merge Patch. Los campos con tipo incorrecto o no editables se
+    /// devuelven como errores por campo.
+    pub fn from_merge_patch(patch: serde_json::Value) -> Result<Self, FieldErrors> {
+        let serde_json::Value::Object(fields) = patch else {
+            return Err(FieldErrors::from([(
+                "body".to_string(),
+                vec!["Merge patch must be a JSON object".to_string()],
+            )]));
+        };
+
+        let mut req = Self::default();
+        let mut errors = FieldErrors::new();
+        for (name, value) in fields {
+            let result = match name.as_str() {
+                "tags" => patch_field(value).map(|patch| req.tags = patch),
+                "category" => patch_field(value).map(|patch| req.category = patch),
+                "notes" => patch_field(value).map(|patch| req.notes = patch),
+                "author_name" => patch_field(value).map(|patch| req.author_name = patch),
+                "location_info" => patch_field(value).map(|patch| req.location_info = patch),
+                "difficulty" => patch_field(value).map(|patch| req.difficulty = patch),
+                _ => Err("Field is not editable".to_string()),
+            };
+            if let Err(e) = result {
+                errors.entry(name).or_default().push(e);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(req)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn patch_field<T: serde::de::DeserializeOwned>(
+    value: serde_json::Value,
+) -> Result<Patch<T>, String> {
+    match value {
+        serde_json::Value::Null => Ok(Patch::Null),
+        value => serde_json::from_value(value)
+            .map(Patch::Value)
+            .map_err(|e| e.to_string()),
+    }
+}
+
+/// Aplica un JSON Merge Patch (RFC 7396) sobre `target`
+pub fn apply_merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(serde_json::Map::new());
+    }
+    if let serde_json::Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                apply_merge_patch(
+                    target.entry(key.clone()).or_insert(serde_json::Value::Null),
+                    value,
+                );
+            }
+        }
+    }
+}
+
+/// Errores de `validator` en el formato de `FieldErrors`
+pub fn field_errors(errors: &validator::ValidationErrors) -> FieldErrors {
+    errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let messages = errors
+                .iter()
+                .map(|e| e.message.as_deref().unwrap_or(&e.code).to_string())
+                .collect();
+            (field.to_string(), messages)
+        })
+        .collect()
+}
+
+fn invalid(code: &'static str, message: String) -> ValidationError {
+    ValidationError::new(code).with_message(message.into())
+}
+
+fn validate_patch_tags(tags: &Patch<Vec<String>>) -> Result<(), ValidationError> {
+    let Some(tags) = tags.value() else {
+        return Ok(());
+    };
+    if tags.len() > MAX_CAPTURE_TAGS {
+        return Err(invalid(
+            "length",
+            format!("At most {} tags are allowed", MAX_CAPTURE_TAGS),
+        ));
+    }
+    if tags.iter().any(|tag| tag.chars().count() > 50) {
+        return Err(invalid(
+            "length",
+            "Tags must be at most 50 characters".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_patch_category(category: &Patch<String>) -> Result<(), ValidationError> {
+    match category.value() {
+        Some(category) if category.is_empty() || category.chars().count() > 50 => Err(invalid(
+            "length",
+            "Category must be between 1 and 50 characters".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_patch_notes(notes: &Patch<String>) -> Result<(), ValidationError> {
+    match notes.value() {
+        Some(notes) if notes.chars().count() > MAX_NOTES_CHARS => Err(invalid(
+            "length",
+            format!("Notes must be at most {} characters", MAX_NOTES_CHARS),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_patch_author_name(author_name: &Patch<String>) -> Result<(), ValidationError> {
+    match author_name.value() {
+        Some(name) if name.is_empty() || name.chars().count() > 100 => Err(invalid(
+            "length",
+            "Author name must be between 1 and 100 characters".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_patch_location_info(
+    location_info: &Patch<serde_json::Value>,
+) -> Result<(), ValidationError> {
+    match location_info.value() {
+        Some(info) if !info.is_object() => Err(invalid(
+            "type",
+            "Location info must be an object".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_patch_difficulty(difficulty: &Patch<String>) -> Result<(), ValidationError> {
+    match difficulty.value() {
+        Some(difficulty) if !DIFFICULTIES.contains(&difficulty.as_str()) => Err(invalid(
+            "difficulty",
+            format!("Difficulty must be one of {}", DIFFICULTIES.join(", ")),
+        )),
+        _ => Ok(()),
+    }
 }
 
 /// Respuesta paginada de capturas
 #[derive(Debug, Serialize)]
 pub struct CaptureListResponse {
     pub captures: Vec<Capture>,
-    pub total: i64,
+    /// Solo si se pidió (`include_total`); contar es caro en tablas grandes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
     pub page: i32,
     pub limit: i32,
     pub has_more: bool,
+    /// Cursor opaco de la página siguiente (paginación por keyset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// Request para presigned URL
@@ -73,6 +347,9 @@ pub struct PresignedUrlRequest {
     pub filename: String,
     #[validate(length(min = 1, max = 100))]
     pub content_type: String,
+    /// Tamaño esperado del archivo; acota el content-length-range de la política POST
+    #[validate(range(min = 1))]
+    pub expected_size_bytes: Option<i64>,
 }
 
 /// Response de presigned URL
@@ -84,6 +361,124 @@ pub struct PresignedUrlResponse {
     pub expires_in_seconds: i64,
 }
 
+/// Response de presigned POST: el cliente envía `fields` + el archivo (último campo) a `url`
+#[derive(Debug, Serialize)]
+pub struct PresignedPostResponse {
+    pub url: String,
+    pub fields: std::collections::BTreeMap<String, String>,
+    pub object_key: String,
+    pub public_url: String,
+    pub max_size_bytes: i64,
+    pub expires_in_seconds: i64,
+}
+
+/// Request para iniciar un upload multipart
+#[derive(Debug, Deserialize, Validate)]
+pub struct InitiateMultipartRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub filename: String,
+    #[validate(length(min = 1, max = 100))]
+    pub content_type: String,
+    /// Tamaño total del archivo; determina el tamaño y número de partes
+    #[validate(range(min = 1))]
+    pub total_size_bytes: i64,
+}
+
+/// Response de inicio de upload multipart
+#[derive(Debug, Serialize)]
+pub struct InitiateMultipartResponse {
+    pub upload_id: String,
+    pub object_key: String,
+    pub public_url: String,
+    pub part_size_bytes: i64,
+    pub part_count: i32,
+}
+
+/// Identifica un upload multipart en curso
+#[derive(Debug, Deserialize, Validate)]
+pub struct MultipartUploadRef {
+    #[validate(length(min = 1, max = 1024))]
+    pub object_key: String,
+    #[validate(length(min = 1, max = 1024))]
+    pub upload_id: String,
+}
+
+/// Request para firmar URLs de partes
+#[derive(Debug, Deserialize, Validate)]
+pub struct PresignPartsRequest {
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub upload: MultipartUploadRef,
+    #[validate(length(min = 1, max = 100))]
+    pub part_numbers: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PresignedPart {
+    pub part_number: i32,
+    pub upload_url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PresignPartsResponse {
+    pub parts: Vec<PresignedPart>,
+    pub expires_in_seconds: i64,
+}
+
+/// Parte subida por el cliente: número y ETag devuelto por S3
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompletedUploadPart {
+    pub part_number: i32,
+    pub etag: String,
+    #[serde(skip_serializing_if = "Option::is_none", skip_deserializing)]
+    pub size_bytes: Option<i64>,
+}
+
+/// Request para completar un upload multipart
+#[derive(Debug, Deserialize, Validate)]
+pub struct CompleteMultipartRequest {
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub upload: MultipartUploadRef,
+    #[validate(length(min = 1, max = 10000))]
+    pub parts: Vec<CompletedUploadPart>,
+}
+
+/// Estado de un upload multipart: partes que S3 ya tiene, para reanudar
+#[derive(Debug, Serialize)]
+pub struct MultipartStatusResponse {
+    pub object_key: String,
+    pub upload_id: String,
+    pub parts: Vec<CompletedUploadPart>,
+}
+
+/// Response de upload multipart completado
+#[derive(Debug, Serialize)]
+pub struct CompleteMultipartResponse {
+    pub object_key: String,
+    pub public_url: String,
+    pub content_type: Option<String>,
+    pub size_bytes: i64,
+}
+
+/// Firma de una URL del backend local (`/files/{key}`)
+#[derive(Debug, Deserialize)]
+pub struct SignedFileQuery {
+    pub expires: i64,
+    pub signature: String,
+}
+
+/// Response de upload directo al servidor
+#[derive(Debug, Serialize)]
+pub struct UploadResponse {
+    pub object_key: String,
+    pub public_url: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub width: u32,
+    pub height: u32,
+}
+
 /// Sync request desde dispositivo
 #[derive(Debug, Deserialize, Validate)]
 pub struct SyncUploadRequest {
@@ -137,6 +532,65 @@ pub struct DeviceUpload {
     pub created_at: DateTime<Utc>,
 }
 
+/// Tipo de trabajo en la cola de procesamiento de capturas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobType {
+    /// Análisis AI con Gemini
+    Analyze,
+    /// Renditions de thumbnail y placeholders
+    Thumbnail,
+    /// Dimensiones y orientación EXIF
+    Exif,
+    /// Hash SHA-256 del contenido
+    Hash,
+}
+
+impl JobType {
+    /// Jobs de procesamiento de imagen, independientes del estado del análisis AI
+    pub const IMAGE_PROCESSING: [JobType; 3] = [JobType::Thumbnail, JobType::Exif, JobType::Hash];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobType::Analyze => "analyze",
+            JobType::Thumbnail => "thumbnail",
+            JobType::Exif => "exif",
+            JobType::Hash => "hash",
+        }
+    }
+}
+
+impl std::str::FromStr for JobType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "analyze" => Ok(JobType::Analyze),
+            "thumbnail" => Ok(JobType::Thumbnail),
+            "exif" => Ok(JobType::Exif),
+            "hash" => Ok(JobType::Hash),
+            other => Err(format!("Unknown job type '{}'", other)),
+        }
+    }
+}
+
+/// Job reclamado de la cola
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: Uuid,
+    pub capture_id: Uuid,
+    pub job_type: JobType,
+    pub attempts: i32,
+}
+
+/// Borrado de objeto de almacenamiento reclamado del outbox
+#[derive(Debug, Clone)]
+pub struct StorageDeletion {
+    pub id: Uuid,
+    pub object_key: String,
+    pub attempts: i32,
+}
+
 /// Analysis result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalysisResult {
@@ -176,22 +630,541 @@ impl<T> ApiResponse<T> {
             timestamp: Utc::now(),
         }
     }
+
+    /// Error with details in `data` (e.g. per-field validation errors)
+    pub fn error_with_data(message: String, data: T) -> Self {
+        Self {
+            success: false,
+            data: Some(data),
+            error: Some(message),
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Campo por el que se ordenan los listados de capturas
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureSort {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    Confidence,
 }
 
-/// Pagination parameters
+impl CaptureSort {
+    pub fn column(&self) -> &'static str {
+        match self {
+            CaptureSort::CreatedAt => "created_at",
+            CaptureSort::UpdatedAt => "updated_at",
+            CaptureSort::Confidence => "confidence",
+        }
+    }
+}
+
+/// Sentido del orden
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+/// Cómo se combinan los tags del filtro: alguno (`any`) o todos (`all`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+/// Query de `GET /captures`: paginación, filtros y orden
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct CaptureListQuery {
+    pub page: Option<i32>,
+    pub limit: Option<i32>,
+    /// `next_cursor` de la respuesta anterior; si viene, `page` se ignora
+    #[validate(length(max = 512))]
+    pub cursor: Option<String>,
+    /// Incluir `total` (por defecto solo en paginación por página)
+    pub include_total: Option<bool>,
+    pub user_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 50))]
+    pub category: Option<String>,
+    /// Lista separada por comas
+    #[validate(length(max = 500))]
+    pub tags: Option<String>,
+    #[serde(default)]
+    pub tags_match: TagMatch,
+    #[validate(length(min = 1, max = 50))]
+    pub difficulty: Option<String>,
+    pub verified: Option<bool>,
+    pub is_public: Option<bool>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub min_confidence: Option<f64>,
+    #[serde(default)]
+    pub sort: CaptureSort,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+impl CaptureListQuery {
+    /// Tags del filtro, sin espacios ni entradas vacías
+    pub fn tag_list(&self) -> Vec<String> {
+        self.tags
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(|tag| tag.trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+}
+
+/// Query de `GET /captures/search`
+#[derive(Debug, Deserialize, Validate)]
+pub struct CaptureSearchQuery {
+    /// Texto a buscar (sintaxis web: `"frase exacta"`, `or`, `-excluir`)
+    #[validate(length(min = 1, max = 200))]
+    pub q: String,
+    pub user_id: Option<Uuid>,
+    pub page: Option<i32>,
+    pub limit: Option<i32>,
+}
+
+/// Resultado de búsqueda: la captura con su relevancia y el fragmento resaltado
+#[derive(Debug, Serialize)]
+pub struct CaptureSearchHit {
+    #[serde(flatten)]
+    pub capture: Capture,
+    pub rank: f32,
+    /// Nombre y descripción con las coincidencias entre `<mark>` y `</mark>`; el resto del
+    /// texto va escapado como HTML
+    pub headline: String,
+}
+
+/// Respuesta de `GET /captures/search`, ordenada por relevancia
+#[derive(Debug, Serialize)]
+pub struct CaptureSearchResponse {
+    pub results: Vec<CaptureSearchHit>,
+    pub page: i32,
+    pub limit: i32,
+    pub has_more: bool,
+}
+
+/// Query de `GET /captures/nearby`
+#[derive(Debug, Deserialize, Validate)]
+pub struct NearbyQuery {
+    #[validate(range(min = -90.0, max = 90.0))]
+    pub lat: f64,
+    #[validate(range(min = -180.0, max = 180.0))]
+    pub lon: f64,
+    #[validate(range(min = 1.0, max = 50000.0))]
+    pub radius_m: f64,
+    pub user_id: Option<Uuid>,
+    pub limit: Option<i32>,
+}
+
+/// Query de `GET /captures/bbox`. Si `min_lon > max_lon` la caja cruza el
+/// antimeridiano. La distancia se mide desde `lat`/`lon` o, sin ellos, desde el
+/// centro de la caja.
+#[derive(Debug, Deserialize, Validate)]
+pub struct BboxQuery {
+    #[validate(range(min = -90.0, max = 90.0))]
+    pub min_lat: f64,
+    #[validate(range(min = -180.0, max = 180.0))]
+    pub min_lon: f64,
+    #[validate(range(min = -90.0, max = 90.0))]
+    pub max_lat: f64,
+    #[validate(range(min = -180.0, max = 180.0))]
+    pub max_lon: f64,
+    #[validate(range(min = -90.0, max = 90.0))]
+    pub lat: Option<f64>,
+    #[validate(range(min = -180.0, max = 180.0))]
+    pub lon: Option<f64>,
+    pub user_id: Option<Uuid>,
+    pub limit: Option<i32>,
+}
+
+impl BboxQuery {
+    /// Punto desde el que se miden las distancias
+    pub fn reference_point(&self) -> (f64, f64) {
+        let center_lat = (self.min_lat + self.max_lat) / 2.0;
+        let mut center_lon = (self.min_lon + self.max_lon) / 2.0;
+        if self.min_lon > self.max_lon {
+            center_lon += if center_lon > 0.0 { -180.0 } else { 180.0 };
+        }
+        (
+            self.lat.unwrap_or(center_lat),
+            self.lon.unwrap_or(center_lon),
+        )
+    }
+}
+
+/// Captura con su distancia (en metros) al punto de la consulta
+#[derive(Debug, Serialize)]
+pub struct GeoCaptureHit {
+    #[serde(flatten)]
+    pub capture: Capture,
+    pub distance_m: f64,
+}
+
+/// Respuesta de `GET /captures/nearby` y `GET /captures/bbox`, de la más cercana a
+/// la más lejana
+#[derive(Debug, Serialize)]
+pub struct GeoCapturesResponse {
+    pub results: Vec<GeoCaptureHit>,
+    pub limit: i32,
+    pub has_more: bool,
+}
+
+/// Query de `GET /captures/clusters`
+#[derive(Debug, Deserialize, Validate)]
+pub struct ClusterQuery {
+    /// Nivel de zoom del mapa (0-20); fija el tamaño de la celda de la rejilla
+    #[validate(range(max = 20))]
+    pub z: u8,
+    /// `oeste,sur,este,norte` en grados
+    pub bbox: String,
+    pub user_id: Option<Uuid>,
+    pub limit: Option<i32>,
+}
+
+impl ClusterQuery {
+    /// Celdas de rejilla por tile de 256px en cada eje (~64px por celda)
+    const CELLS_PER_TILE: f64 = 4.0;
+
+    /// Caja `bbox` como `BboxQuery`, o el motivo por el que no es válida
+    pub fn bounds(&self) -> Result<BboxQuery, String> {
+        let values = self
+            .bbox
+            .split(',')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| "bbox must be four numbers: west,south,east,north".to_string())?;
+        let [west, south, east, north] = values[..] else {
+            return Err("bbox must be four numbers: west,south,east,north".to_string());
+        };
+
+        let bounds = BboxQuery {
+            min_lat: south,
+            min_lon: west,
+            max_lat: north,
+            max_lon: east,
+            lat: None,
+            lon: None,
+            user_id: self.user_id,
+            limit: None,
+        };
+        bounds
+            .validate()
+            .map_err(|e| format!("Invalid bbox: {}", e))?;
+        if south > north {
+            return Err("bbox south must not be greater than north".to_string());
+        }
+        Ok(bounds)
+    }
+
+    /// Lado de la celda de la rejilla en grados para este zoom
+    pub fn cell_size_deg(&self) -> f64 {
+        360.0 / 2f64.powi(self.z as i32) / Self::CELLS_PER_TILE
+    }
+}
+
+/// Grupo de capturas de una celda de la rejilla
+#[derive(Debug, Serialize)]
+pub struct CaptureCluster {
+    pub count: i64,
+    /// Centroide de las capturas del grupo
+    pub latitude: f64,
+    pub longitude: f64,
+    pub top_category: Option<String>,
+    /// Captura representativa (con thumbnail y la mayor confianza)
+    pub representative_id: Uuid,
+    pub thumbnail_url: Option<String>,
+    #[serde(skip)]
+    pub thumbnail_key: Option<String>,
+    #[serde(skip)]
+    pub representative_is_public: bool,
+}
+
+/// Respuesta de `GET /captures/clusters`
+#[derive(Debug, Serialize)]
+pub struct CaptureClustersResponse {
+    pub zoom: u8,
+    pub cell_size_deg: f64,
+    pub clusters: Vec<CaptureCluster>,
+    /// `true` si se cortó en `limit` grupos
+    pub truncated: bool,
+}
+
+/// Query de `GET /tags`
+#[derive(Debug, Deserialize, Validate)]
+pub struct TagListQuery {
+    /// Autocompletado: solo tags que empiezan por este texto
+    #[validate(length(min = 1, max = 50))]
+    pub prefix: Option<String>,
+    pub limit: Option<i32>,
+}
+
+/// Query de `GET /tags/trending`
+#[derive(Debug, Deserialize, Validate)]
+pub struct TrendingTagsQuery {
+    /// Ventana en días (por defecto 7)
+    #[validate(range(min = 1, max = 365))]
+    pub days: Option<i32>,
+    pub limit: Option<i32>,
+}
+
+/// Query de `GET /tags/{name}/captures`
 #[derive(Debug, Deserialize)]
-pub struct PaginationParams {
+pub struct TagCapturesQuery {
     pub page: Option<i32>,
     pub limit: Option<i32>,
-    pub cursor: Option<Uuid>,
 }
 
-impl Default for PaginationParams {
-    fn default() -> Self {
+/// Query de `GET /captures/{id}/notes/history`
+#[derive(Debug, Deserialize)]
+pub struct NoteHistoryQuery {
+    pub page: Option<i32>,
+    pub limit: Option<i32>,
+}
+
+/// Versión guardada de las notas de una captura (`None` si se borraron)
+#[derive(Debug, Serialize)]
+pub struct NoteRevision {
+    pub id: i64,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Historial de notas, de la más reciente a la más antigua
+#[derive(Debug, Serialize)]
+pub struct NoteHistoryResponse {
+    pub revisions: Vec<NoteRevision>,
+    pub page: i32,
+    pub limit: i32,
+    pub has_more: bool,
+}
+
+/// Acción registrada en el historial de una captura
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureAction {
+    Created,
+    Updated,
+    Published,
+    Unpublished,
+    /// Movida a la papelera
+    Deleted,
+    Restored,
+    /// Borrada definitivamente
+    Purged,
+    /// Resultado del análisis AI
+    Analyzed,
+    /// Metadatos calculados por los jobs de imagen (EXIF, placeholder, hash)
+    Processed,
+    TagsMerged,
+}
+
+impl CaptureAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CaptureAction::Created => "created",
+            CaptureAction::Updated => "updated",
+            CaptureAction::Published => "published",
+            CaptureAction::Unpublished => "unpublished",
+            CaptureAction::Deleted => "deleted",
+            CaptureAction::Restored => "restored",
+            CaptureAction::Purged => "purged",
+            CaptureAction::Analyzed => "analyzed",
+            CaptureAction::Processed => "processed",
+            CaptureAction::TagsMerged => "tags_merged",
+        }
+    }
+
+    /// Whether the event stores a field diff. Deletions only record who and when,
+    /// so the history does not keep a copy of a capture its owner removed.
+    pub fn records_changes(&self) -> bool {
+        !matches!(self, CaptureAction::Deleted | CaptureAction::Purged)
+    }
+}
+
+/// Actores de sistema del historial; los usuarios aparecen como `user:{uuid}`
+pub const ACTOR_ANONYMOUS: &str = "anonymous";
+pub const ACTOR_ADMIN: &str = "admin";
+pub const ACTOR_WORKER: &str = "system:worker";
+pub const ACTOR_TRASH_PURGE: &str = "system:trash-purge";
+
+/// Entrada del historial de una captura (append-only)
+#[derive(Debug, Serialize)]
+pub struct CaptureEvent {
+    pub id: i64,
+    pub capture_id: Uuid,
+    pub actor: String,
+    pub action: String,
+    /// `{"campo": {"before": ..., "after": ...}}` por cada campo cambiado
+    pub changes: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Query de `GET /captures/{id}/history`
+#[derive(Debug, Deserialize)]
+pub struct CaptureHistoryQuery {
+    pub page: Option<i32>,
+    pub limit: Option<i32>,
+}
+
+/// Historial de una captura, del evento más reciente al más antiguo
+#[derive(Debug, Serialize)]
+pub struct CaptureHistoryResponse {
+    pub events: Vec<CaptureEvent>,
+    pub page: i32,
+    pub limit: i32,
+    pub has_more: bool,
+}
+
+/// Tag con el número de capturas activas que lo usan
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    pub name: String,
+    pub count: i64,
+}
+
+/// Tag en tendencia: usos en la ventana y en la ventana anterior del mismo tamaño
+#[derive(Debug, Serialize)]
+pub struct TrendingTag {
+    pub name: String,
+    pub count: i64,
+    pub previous_count: i64,
+}
+
+/// Request de `POST /admin/tags/merge`
+#[derive(Debug, Deserialize, Validate)]
+pub struct MergeTagsRequest {
+    /// Tags a fusionar (se borran)
+    #[validate(length(min = 1, max = 100))]
+    pub sources: Vec<String>,
+    /// Tag resultante; se normaliza antes de fusionar
+    #[validate(length(min = 1, max = 100))]
+    pub target: String,
+}
+
+/// Resultado de una fusión de tags
+#[derive(Debug, Serialize)]
+pub struct MergeTagsResponse {
+    pub target: String,
+    /// Tags de `sources` que existían y se fusionaron
+    pub merged: Vec<String>,
+    /// Capturas cuyo array `tags` se reescribió
+    pub affected_captures: u64,
+}
+
+/// Posición de keyset en un listado: valor de la columna de orden de la última
+/// captura devuelta más su id. Viaja al cliente como base64url de JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureCursor {
+    #[serde(rename = "s")]
+    pub sort: CaptureSort,
+    #[serde(rename = "o")]
+    pub order: SortOrder,
+    /// `created_at` o `updated_at`, según `sort`
+    #[serde(rename = "t", default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// `confidence` (puede ser nula)
+    #[serde(rename = "c", default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    pub id: Uuid,
+}
+
+impl CaptureCursor {
+    /// Cursor que apunta justo después de `capture`
+    pub fn after(capture: &Capture, sort: CaptureSort, order: SortOrder) -> Self {
+        let (timestamp, confidence) = match sort {
+            CaptureSort::CreatedAt => (Some(capture.created_at), None),
+            CaptureSort::UpdatedAt => (Some(capture.updated_at), None),
+            CaptureSort::Confidence => (None, capture.confidence),
+        };
         Self {
-            page: Some(1),
-            limit: Some(20),
-            cursor: None,
+            sort,
+            order,
+            timestamp,
+            confidence,
+            id: capture.id,
         }
     }
+
+    pub fn encode(&self) -> String {
+        general_purpose::URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    /// `None` si el cursor está corrupto
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let bytes = general_purpose::URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let cursor: Self = serde_json::from_slice(&bytes).ok()?;
+        let has_value = match cursor.sort {
+            CaptureSort::CreatedAt | CaptureSort::UpdatedAt => cursor.timestamp.is_some(),
+            CaptureSort::Confidence => true,
+        };
+        has_value.then_some(cursor)
+    }
+}
+
+/// Query de `DELETE /captures/{id}`
+#[derive(Debug, Deserialize)]
+pub struct DeleteCaptureQuery {
+    /// Borrado definitivo (base de datos y almacenamiento) en lugar de papelera
+    #[serde(default)]
+    pub permanent: bool,
+}
+
+/// Query de `GET /captures/trash`: el usuario sale de la cabecera `X-User-Id`
+#[derive(Debug, Deserialize)]
+pub struct TrashQuery {
+    pub page: Option<i32>,
+    pub limit: Option<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_list_trims_lowercases_and_drops_empty_entries() {
+        let query = CaptureListQuery {
+            tags: Some(" Volcan,,LAGO , ,cascada ".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(query.tag_list(), vec!["volcan", "lago", "cascada"]);
+
+        assert!(CaptureListQuery::default().tag_list().is_empty());
+    }
+
+    #[test]
+    fn cursor_round_trips_through_its_encoding() {
+        let cursor = CaptureCursor {
+            sort: CaptureSort::Confidence,
+            order: SortOrder::Asc,
+            timestamp: None,
+            confidence: Some(0.75),
+            id: Uuid::nil(),
+        };
+        let decoded = CaptureCursor::decode(&cursor.encode()).unwrap();
+        assert_eq!(decoded.sort, CaptureSort::Confidence);
+        assert_eq!(decoded.order, SortOrder::Asc);
+        assert_eq!(decoded.confidence, Some(0.75));
+        assert_eq!(decoded.id, Uuid::nil());
+    }
 }
@@ -0,0 +1,316 @@
+use std::path::{Component, Path, PathBuf};
+
+use aws_sigv4::sign::v4::calculate_signature;
+
+use super::{ObjectMetadata, ObjectStore, StoreFuture, StoredObject};
+use crate::config::StorageConfig;
+
+/// Local filesystem backend for development. Objects live under `root` and are
+/// served by the `/files/{key}` route, which only accepts signed URLs.
+pub struct LocalStore {
+    root: PathBuf,
+    base_url: String,
+    signing_secret: String,
+}
+
+impl LocalStore {
+    pub fn new(config: &StorageConfig) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        if config.storage_signing_secret.is_empty() {
+            return Err("STORAGE_SIGNING_SECRET is required for the local storage backend".into());
+        }
+
+        let root = PathBuf::from(&config.local_storage_path);
+        std::fs::create_dir_all(&root)?;
+
+        log::info!("Local storage initialized at: {}", root.display());
+
+        Ok(Self {
+            root,
+            base_url: config
+                .local_storage_base_url
+                .trim_end_matches('/')
+                .to_string(),
+            signing_secret: config.storage_signing_secret.clone(),
+        })
+    }
+
+    /// Map an object key to a path under `root`, rejecting anything that could escape it
+    fn path_for(
+        &self,
+        object_key: &str,
+    ) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+        let relative = Path::new(object_key);
+        let is_safe = !object_key.is_empty()
+            && !object_key.contains('\\')
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+
+        if !is_safe {
+            return Err(format!("Invalid object key '{}'", object_key).into());
+        }
+
+        Ok(self.root.join(relative))
+    }
+
+    /// Signature over method, key and expiry. `expires = 0` never expires and is only
+    /// valid for GET; it backs the stable public URLs.
+    fn sign(&self, method: &str, object_key: &str, expires: i64) -> String {
+        let payload = format!("{}\n{}\n{}", method, object_key, expires);
+        // Plain HMAC-SHA256 (hex) keyed with the signing secret
+        calculate_signature(self.signing_secret.as_bytes(), payload.as_bytes())
+    }
+
+    fn signed_url(&self, method: &str, object_key: &str, expires: i64) -> String {
+        format!(
+            "{}/{}?expires={}&signature={}",
+            self.base_url,
+            object_key,
+            expires,
+            self.sign(method, object_key, expires)
+        )
+    }
+
+    /// Check a signature received by the `/files/{key}` route
+    pub fn verify_signature(
+        &self,
+        method: &str,
+        object_key: &str,
+        expires: i64,
+        signature: &str,
+    ) -> bool {
+        let not_expired = if expires == 0 {
+            method == "GET"
+        } else {
+            expires >= chrono::Utc::now().timestamp()
+        };
+
+        let expected = self.sign(method, object_key, expires);
+        // Constant-time comparison
+        let matches = expected.len() == signature.len()
+            && expected
+                .bytes()
+                .zip(signature.bytes())
+                .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+                == 0;
+
+        not_expired && matches
+    }
+
+    fn expires_at(expires_in_seconds: u64) -> i64 {
+        chrono::Utc::now().timestamp() + expires_in_seconds as i64
+    }
+}
+
+/// Recursively collect the files under `dir`, with keys relative to `root`
+fn collect_files(root: &Path, dir: &Path, objects: &mut Vec<StoredObject>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let path = entry.path();
+
+        if metadata.is_dir() {
+            collect_files(root, &path, objects)?;
+        } else if metadata.is_file() {
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let key = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            objects.push(StoredObject {
+                key,
+                size_bytes: metadata.len() as i64,
+                last_modified: metadata.modified().ok().map(chrono::DateTime::from),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Content type inferred from the key extension (the filesystem keeps no metadata)
+pub fn content_type_for_key(object_key: &str) -> &'static str {
+    let extension = Path::new(object_key)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+
+    match extension.as_deref() {
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        Some("heic") => "image/heic",
+        _ => "application/octet-stream",
+    }
+}
+
+impl ObjectStore for LocalStore {
+    fn put_object<'a>(
+        &'a self,
+        object_key: &'a str,
+        data: Vec<u8>,
+        _content_type: &'a str,
+    ) -> StoreFuture<'a, String> {
+        Box::pin(async move {
+            let path = self.path_for(object_key)?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, &data).await?;
+
+            log::info!("💾 Stored {} ({} bytes)", object_key, data.len());
+            Ok(self.public_url(object_key))
+        })
+    }
+
+    fn put_file<'a>(
+        &'a self,
+        object_key: &'a str,
+        source: &'a Path,
+        _content_type: &'a str,
+    ) -> StoreFuture<'a, String> {
+        Box::pin(async move {
+            let path = self.path_for(object_key)?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let size = tokio::fs::copy(source, &path).await?;
+
+            log::info!("💾 Stored {} ({} bytes)", object_key, size);
+            Ok(self.public_url(object_key))
+        })
+    }
+
+    fn get_object<'a>(&'a self, object_key: &'a str) -> StoreFuture<'a, Vec<u8>> {
+        Box::pin(async move { Ok(tokio::fs::read(self.path_for(object_key)?).await?) })
+    }
+
+    fn delete_object<'a>(&'a self, object_key: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.path_for(object_key)?).await {
+                Ok(()) => Ok(()),
+                // Same as S3: deleting a missing object is not an error
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    fn head_object<'a>(&'a self, object_key: &'a str) -> StoreFuture<'a, Option<ObjectMetadata>> {
+        Box::pin(async move {
+            match tokio::fs::metadata(self.path_for(object_key)?).await {
+                Ok(metadata) if metadata.is_file() => Ok(Some(ObjectMetadata {
+                    size_bytes: metadata.len() as i64,
+                    content_type: Some(content_type_for_key(object_key).to_string()),
+                })),
+                Ok(_) => Ok(None),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    fn presigned_put_url<'a>(
+        &'a self,
+        object_key: &'a str,
+        _content_type: &'a str,
+        expires_in_seconds: u64,
+    ) -> StoreFuture<'a, String> {
+        Box::pin(async move {
+            self.path_for(object_key)?;
+            Ok(self.signed_url("PUT", object_key, Self::expires_at(expires_in_seconds)))
+        })
+    }
+
+    fn presigned_get_url<'a>(
+        &'a self,
+        object_key: &'a str,
+        expires_in_seconds: u64,
+    ) -> StoreFuture<'a, String> {
+        Box::pin(async move {
+            self.path_for(object_key)?;
+            Ok(self.signed_url("GET", object_key, Self::expires_at(expires_in_seconds)))
+        })
+    }
+
+    fn list_objects<'a>(&'a self, prefix: &'a str) -> StoreFuture<'a, Vec<StoredObject>> {
+        Box::pin(async move {
+            let root = self.root.clone();
+            let prefix = prefix.to_string();
+
+            let objects = tokio::task::spawn_blocking(move || -> std::io::Result<_> {
+                // Walk only the top-level directory of the prefix, then filter by the full prefix
+                let top = prefix.split('/').next().unwrap_or_default();
+                let start = root.join(top);
+                let mut objects = Vec::new();
+                if !top.is_empty() && start.is_dir() {
+                    collect_files(&root, &start, &mut objects)?;
+                }
+                objects.retain(|object| object.key.starts_with(&prefix));
+                Ok(objects)
+            })
+            .await??;
+
+            Ok(objects)
+        })
+    }
+
+    fn public_url(&self, object_key: &str) -> String {
+        self.signed_url("GET", object_key, 0)
+    }
+
+    fn object_key_from_url(&self, url: &str) -> Option<String> {
+        let url = url.split(['?', '#']).next().unwrap_or(url);
+        url.strip_prefix(&format!("{}/", self.base_url))
+            .filter(|key| !key.is_empty())
+            .map(String::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> LocalStore {
+        LocalStore {
+            root: std::env::temp_dir(),
+            base_url: "http://localhost:8080/files".to_string(),
+            signing_secret: "Jefe".to_string(),
+        }
+    }
+
+    #[test]
+    fn signature_is_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            calculate_signature(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn verifies_only_matching_unexpired_signatures() {
+        let store = store();
+        let expires = LocalStore::expires_at(60);
+        let signature = store.sign("PUT", "captures/a/b.jpg", expires);
+
+        assert!(store.verify_signature("PUT", "captures/a/b.jpg", expires, &signature));
+        assert!(!store.verify_signature("GET", "captures/a/b.jpg", expires, &signature));
+        assert!(!store.verify_signature("PUT", "captures/a/c.jpg", expires, &signature));
+        assert!(!store.verify_signature(
+            "PUT",
+            "captures/a/b.jpg",
+            1,
+            &store.sign("PUT", "captures/a/b.jpg", 1)
+        ));
+        assert!(!store.verify_signature(
+            "PUT",
+            "captures/a/b.jpg",
+            0,
+            &store.sign("PUT", "captures/a/b.jpg", 0)
+        ));
+    }
+}
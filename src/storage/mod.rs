@@ -2,14 +2,267 @@ use aws_config::{BehaviorVersion, Region};
 use aws_credential_types::Credentials;
 use aws_sdk_s3::config::SharedCredentialsProvider;
 use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
 use aws_sdk_s3::{Client, Config};
+use futures_util::future::BoxFuture;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
+use uuid::Uuid;
 
 use crate::config::StorageConfig;
+use crate::models::Capture;
+
+mod local;
+mod post_policy;
+mod read_urls;
+
+pub use local::{content_type_for_key, LocalStore};
+pub use read_urls::ReadUrlSigner;
+
+/// Future returned by [`ObjectStore`] operations
+pub type StoreFuture<'a, T> = BoxFuture<'a, Result<T, Box<dyn std::error::Error + Send + Sync>>>;
+
+/// Object storage backend used by handlers and workers.
+/// Implemented by [`S3Service`] and [`LocalStore`]; selected by `STORAGE_BACKEND`.
+pub trait ObjectStore: Send + Sync {
+    /// Store bytes under `object_key`; returns the public URL
+    fn put_object<'a>(
+        &'a self,
+        object_key: &'a str,
+        data: Vec<u8>,
+        content_type: &'a str,
+    ) -> StoreFuture<'a, String>;
+
+    /// Store the contents of a local file without loading it into memory;
+    /// returns the public URL
+    fn put_file<'a>(
+        &'a self,
+        object_key: &'a str,
+        path: &'a Path,
+        content_type: &'a str,
+    ) -> StoreFuture<'a, String>;
+
+    fn get_object<'a>(&'a self, object_key: &'a str) -> StoreFuture<'a, Vec<u8>>;
+
+    fn delete_object<'a>(&'a self, object_key: &'a str) -> StoreFuture<'a, ()>;
+
+    /// Size and type of an object; `None` when it doesn't exist
+    fn head_object<'a>(&'a self, object_key: &'a str) -> StoreFuture<'a, Option<ObjectMetadata>>;
+
+    /// URL the client can PUT the object to until it expires
+    fn presigned_put_url<'a>(
+        &'a self,
+        object_key: &'a str,
+        content_type: &'a str,
+        expires_in_seconds: u64,
+    ) -> StoreFuture<'a, String>;
+
+    /// URL the object can be downloaded from until it expires
+    fn presigned_get_url<'a>(
+        &'a self,
+        object_key: &'a str,
+        expires_in_seconds: u64,
+    ) -> StoreFuture<'a, String>;
+
+    /// Every object whose key starts with `prefix`
+    fn list_objects<'a>(&'a self, prefix: &'a str) -> StoreFuture<'a, Vec<StoredObject>>;
+
+    fn public_url(&self, object_key: &str) -> String;
+
+    /// Inverse of `public_url`: `None` for URLs outside this store
+    fn object_key_from_url(&self, url: &str) -> Option<String>;
+}
+
+/// Key prefix under which a user's uploads are issued
+pub fn user_key_prefix(user_id: Option<Uuid>) -> String {
+    match user_id {
+        Some(id) => format!("captures/{}/", id),
+        None => "captures/anonymous/".to_string(),
+    }
+}
+
+/// Keys issued before per-user prefixes: `captures/{timestamp}/{uuid}.{ext}`
+pub fn is_legacy_object_key(object_key: &str) -> bool {
+    match object_key.split('/').collect::<Vec<_>>()[..] {
+        ["captures", timestamp, filename] => {
+            !timestamp.is_empty()
+                && timestamp.chars().all(|c| c.is_ascii_digit())
+                && filename
+                    .split_once('.')
+                    .is_some_and(|(stem, _)| Uuid::parse_str(stem).is_ok())
+        }
+        _ => false,
+    }
+}
+
+/// Generate unique object key scoped to the uploading user
+pub fn generate_object_key(user_id: Option<Uuid>, filename: &str) -> String {
+    let uuid = Uuid::new_v4();
+    let timestamp = chrono::Utc::now().timestamp();
+    let extension = std::path::Path::new(filename)
+        .extension()
+        .and_then(|s| s.to_str())
+        .filter(|ext| ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or("jpg");
+
+    format!(
+        "{}{}/{}.{}",
+        user_key_prefix(user_id),
+        timestamp,
+        uuid,
+        extension
+    )
+}
+
+/// Temporary file an upload is spooled to while it is validated; removed on drop
+pub struct SpoolFile {
+    path: PathBuf,
+}
+
+impl SpoolFile {
+    pub fn new() -> Self {
+        Self {
+            path: std::env::temp_dir().join(format!("upload-{}", Uuid::new_v4())),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Default for SpoolFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to remove spool file {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
 
 pub struct S3Service {
     client: Client,
     bucket: String,
+    region: String,
+    endpoint: Option<String>,
+    force_path_style: bool,
+    public_base_url: Option<String>,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+/// Origin URL of a bucket (no CDN), in the same addressing style the client uses:
+/// - custom endpoint, path style: `{endpoint}/{bucket}`
+/// - custom endpoint, virtual hosted: `{scheme}://{bucket}.{endpoint host}`
+/// - AWS, path style: `https://s3.{region}.amazonaws.com/{bucket}`
+/// - AWS, virtual hosted: `https://{bucket}.s3.{region}.amazonaws.com`
+fn bucket_url(
+    endpoint: Option<&str>,
+    region: &str,
+    force_path_style: bool,
+    bucket: &str,
+) -> String {
+    match (endpoint.map(|e| e.trim_end_matches('/')), force_path_style) {
+        (Some(endpoint), true) => format!("{}/{}", endpoint, bucket),
+        (Some(endpoint), false) => match endpoint.split_once("://") {
+            Some((scheme, host)) => format!("{}://{}.{}", scheme, bucket, host),
+            None => format!("{}.{}", bucket, endpoint),
+        },
+        (None, true) => format!("https://s3.{}.amazonaws.com/{}", region, bucket),
+        (None, false) => format!("https://{}.s3.{}.amazonaws.com", bucket, region),
+    }
+}
+
+/// Base URL objects are served from: the CDN / `PUBLIC_BASE_URL` when set,
+/// otherwise the bucket origin
+fn public_base(
+    endpoint: Option<&str>,
+    region: &str,
+    force_path_style: bool,
+    public_base_url: Option<&str>,
+    bucket: &str,
+) -> String {
+    match public_base_url {
+        Some(base) => base.trim_end_matches('/').to_string(),
+        None => bucket_url(endpoint, region, force_path_style, bucket),
+    }
+}
+
+/// Public URL of an object: `{public base}/{key}`
+fn object_url(
+    endpoint: Option<&str>,
+    region: &str,
+    force_path_style: bool,
+    public_base_url: Option<&str>,
+    bucket: &str,
+    object_key: &str,
+) -> String {
+    format!(
+        "{}/{}",
+        public_base(endpoint, region, force_path_style, public_base_url, bucket),
+        object_key
+    )
+}
+
+/// Storage keys of a capture's image and thumbnail. Rows created before keys
+/// were stored fall back to parsing the URL.
+pub fn capture_object_keys(
+    object_store: &dyn ObjectStore,
+    capture: &Capture,
+) -> (Option<String>, Option<String>) {
+    let image_key = capture
+        .image_key
+        .clone()
+        .or_else(|| object_store.object_key_from_url(&capture.image_url));
+    let thumbnail_key = capture.thumbnail_key.clone().or_else(|| {
+        capture
+            .thumbnail_url
+            .as_deref()
+            .and_then(|url| object_store.object_key_from_url(url))
+    });
+    (image_key, thumbnail_key)
+}
+
+/// Size and type of an object as stored in the bucket
+pub struct ObjectMetadata {
+    pub size_bytes: i64,
+    pub content_type: Option<String>,
+}
+
+/// An object found when listing a prefix
+pub struct StoredObject {
+    pub key: String,
+    pub size_bytes: i64,
+    pub last_modified: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A part already stored for an in-progress multipart upload
+pub struct UploadedPart {
+    pub part_number: i32,
+    pub etag: String,
+    pub size_bytes: i64,
+}
+
+/// An incomplete multipart upload found in the bucket
+pub struct PendingMultipartUpload {
+    pub object_key: String,
+    pub upload_id: String,
+    pub initiated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Presigned POST: target URL plus the form fields to send with the file
+pub struct PresignedPost {
+    pub url: String,
+    pub fields: BTreeMap<String, String>,
 }
 
 impl S3Service {
@@ -35,8 +288,8 @@ impl S3Service {
         // Support for custom S3 endpoints (MinIO, LocalStack)
         if let Some(endpoint) = &config.s3_endpoint {
             s3_config_builder = s3_config_builder.endpoint_url(endpoint);
-            s3_config_builder = s3_config_builder.force_path_style(true);
         }
+        s3_config_builder = s3_config_builder.force_path_style(config.s3_force_path_style);
 
         let s3_config = s3_config_builder.build();
         let client = Client::from_conf(s3_config);
@@ -46,134 +299,482 @@ impl S3Service {
         Ok(Self {
             client,
             bucket: config.s3_bucket.clone(),
+            region: config.aws_region.clone(),
+            endpoint: config
+                .s3_endpoint
+                .as_ref()
+                .map(|e| e.trim_end_matches('/').to_string()),
+            force_path_style: config.s3_force_path_style,
+            public_base_url: config
+                .public_base_url
+                .as_ref()
+                .map(|u| u.trim_end_matches('/').to_string()),
+            access_key_id: config.aws_access_key_id.clone(),
+            secret_access_key: config.aws_secret_access_key.clone(),
         })
     }
 
-    /// Generate presigned PUT URL for upload
-    pub async fn generate_presigned_put_url(
+    fn bucket_url(&self) -> String {
+        bucket_url(
+            self.endpoint.as_deref(),
+            &self.region,
+            self.force_path_style,
+            &self.bucket,
+        )
+    }
+
+    fn public_base(&self) -> String {
+        public_base(
+            self.endpoint.as_deref(),
+            &self.region,
+            self.force_path_style,
+            self.public_base_url.as_deref(),
+            &self.bucket,
+        )
+    }
+
+    /// Generate a presigned POST policy restricted to one key, an exact content type
+    /// and a content-length range, so S3 itself rejects oversized or mistyped uploads
+    pub fn generate_presigned_post(
         &self,
         object_key: &str,
         content_type: &str,
+        max_size_bytes: u64,
         expires_in_seconds: u64,
+    ) -> Result<PresignedPost, Box<dyn std::error::Error + Send + Sync>> {
+        let policy = post_policy::PostPolicy {
+            bucket: &self.bucket,
+            key: object_key,
+            content_type,
+            min_size_bytes: 1,
+            max_size_bytes,
+            expires_in_seconds,
+        };
+
+        let fields = post_policy::sign_post_policy(
+            &policy,
+            &self.access_key_id,
+            &self.secret_access_key,
+            &self.region,
+            chrono::Utc::now(),
+        )?;
+
+        Ok(PresignedPost {
+            url: self.bucket_url(),
+            fields,
+        })
+    }
+
+    /// Start a multipart upload; returns the upload id
+    pub async fn create_multipart_upload(
+        &self,
+        object_key: &str,
+        content_type: &str,
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         log::info!(
-            "inicio ******** 2 - generate_presigned_put_url start: {}",
+            "inicio ******** 2 - create_multipart_upload start: {}",
             object_key
         );
-        let presigning_config =
-            PresigningConfig::expires_in(Duration::from_secs(expires_in_seconds))?;
-
-        let presigned_request = self
+        let output = self
             .client
-            .put_object()
+            .create_multipart_upload()
             .bucket(&self.bucket)
             .key(object_key)
             .content_type(content_type)
-            .presigned(presigning_config)
+            .send()
             .await?;
 
-        Ok(presigned_request.uri().to_string())
+        let upload_id = output
+            .upload_id()
+            .ok_or("S3 did not return an upload id")?
+            .to_string();
+        log::info!(
+            "fin ********2 - create_multipart_upload end: {}",
+            object_key
+        );
+        Ok(upload_id)
     }
 
-    /// Generate presigned GET URL for download
-    pub async fn generate_presigned_get_url(
+    /// Generate a presigned PUT URL for one part of a multipart upload
+    pub async fn generate_presigned_part_url(
         &self,
         object_key: &str,
+        upload_id: &str,
+        part_number: i32,
         expires_in_seconds: u64,
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        log::info!(
-            "inicio ******** 2b - generate_presigned_get_url start: {}",
-            object_key
-        );
         let presigning_config =
             PresigningConfig::expires_in(Duration::from_secs(expires_in_seconds))?;
 
         let presigned_request = self
             .client
-            .get_object()
+            .upload_part()
             .bucket(&self.bucket)
             .key(object_key)
+            .upload_id(upload_id)
+            .part_number(part_number)
             .presigned(presigning_config)
             .await?;
 
-        log::info!(
-            "fin ********2b - generate_presigned_get_url end: {}",
-            object_key
-        );
         Ok(presigned_request.uri().to_string())
     }
 
-    /// Generate presigned GET URL for download
-    // (duplicate removed) the presigned GET url method is defined above with logging
-
-    /// Upload bytes directly to S3
-    pub async fn upload_bytes(
+    /// List the parts S3 already has for a multipart upload (used to resume)
+    pub async fn list_uploaded_parts(
         &self,
         object_key: &str,
-        data: Vec<u8>,
-        content_type: &str,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        log::info!(
-            "inicio ******** 2 - upload_bytes start: {} ({} bytes)",
-            object_key,
-            data.len()
-        );
-        self.client
-            .put_object()
-            .bucket(&self.bucket)
-            .key(object_key)
-            .body(data.into())
-            .content_type(content_type)
-            .send()
-            .await?;
+        upload_id: &str,
+    ) -> Result<Vec<UploadedPart>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut parts = Vec::new();
+        let mut marker: Option<String> = None;
+
+        loop {
+            let output = self
+                .client
+                .list_parts()
+                .bucket(&self.bucket)
+                .key(object_key)
+                .upload_id(upload_id)
+                .set_part_number_marker(marker.take())
+                .send()
+                .await?;
 
-        let public = format!("https://{}.s3.amazonaws.com/{}", self.bucket, object_key);
-        log::info!("fin ********2 - upload_bytes end: {}", object_key);
-        Ok(public)
+            parts.extend(output.parts().iter().filter_map(|part| {
+                Some(UploadedPart {
+                    part_number: part.part_number()?,
+                    etag: part.e_tag()?.to_string(),
+                    size_bytes: part.size().unwrap_or(0),
+                })
+            }));
+
+            match output.next_part_number_marker() {
+                Some(next) if output.is_truncated().unwrap_or(false) => {
+                    marker = Some(next.to_string())
+                }
+                _ => break,
+            }
+        }
+
+        Ok(parts)
     }
 
-    /// Download object from S3
-    pub async fn download_object(
+    /// Complete a multipart upload from the (part number, ETag) pairs returned by S3
+    pub async fn complete_multipart_upload(
         &self,
         object_key: &str,
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
-        log::info!("inicio ******** 2 - download_object start: {}", object_key);
-        let response = self
-            .client
-            .get_object()
+        upload_id: &str,
+        parts: &[(i32, String)],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        log::info!(
+            "inicio ******** 2 - complete_multipart_upload start: {} ({} parts)",
+            object_key,
+            parts.len()
+        );
+        let completed_parts = parts
+            .iter()
+            .map(|(part_number, etag)| {
+                CompletedPart::builder()
+                    .part_number(*part_number)
+                    .e_tag(etag)
+                    .build()
+            })
+            .collect::<Vec<_>>();
+
+        self.client
+            .complete_multipart_upload()
             .bucket(&self.bucket)
             .key(object_key)
+            .upload_id(upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(completed_parts))
+                    .build(),
+            )
             .send()
             .await?;
 
-        let body = response.body.collect().await?;
-        let bytes = body.into_bytes();
         log::info!(
-            "fin ********2 - download_object end: {} ({} bytes)",
-            object_key,
-            bytes.len()
+            "fin ********2 - complete_multipart_upload end: {}",
+            object_key
         );
-        Ok(bytes.to_vec())
+        Ok(())
     }
 
-    /// Delete object from S3
-    pub async fn delete_object(
+    /// Abort a multipart upload, discarding the parts already stored
+    pub async fn abort_multipart_upload(
         &self,
         object_key: &str,
+        upload_id: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         self.client
-            .delete_object()
+            .abort_multipart_upload()
             .bucket(&self.bucket)
             .key(object_key)
+            .upload_id(upload_id)
             .send()
             .await?;
 
         Ok(())
     }
 
+    /// List incomplete multipart uploads under a key prefix
+    pub async fn list_multipart_uploads(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<PendingMultipartUpload>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut uploads = Vec::new();
+        let mut key_marker: Option<String> = None;
+        let mut upload_id_marker: Option<String> = None;
+
+        loop {
+            let output = self
+                .client
+                .list_multipart_uploads()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .set_key_marker(key_marker.take())
+                .set_upload_id_marker(upload_id_marker.take())
+                .send()
+                .await?;
+
+            uploads.extend(output.uploads().iter().filter_map(|upload| {
+                Some(PendingMultipartUpload {
+                    object_key: upload.key()?.to_string(),
+                    upload_id: upload.upload_id()?.to_string(),
+                    initiated_at: upload
+                        .initiated()
+                        .and_then(|t| chrono::DateTime::from_timestamp(t.secs(), 0)),
+                })
+            }));
+
+            if !output.is_truncated().unwrap_or(false) {
+                break;
+            }
+            key_marker = output.next_key_marker().map(String::from);
+            upload_id_marker = output.next_upload_id_marker().map(String::from);
+            if key_marker.is_none() {
+                break;
+            }
+        }
+
+        Ok(uploads)
+    }
+}
+
+impl ObjectStore for S3Service {
+    /// Upload bytes directly to S3
+    fn put_object<'a>(
+        &'a self,
+        object_key: &'a str,
+        data: Vec<u8>,
+        content_type: &'a str,
+    ) -> StoreFuture<'a, String> {
+        Box::pin(async move {
+            log::info!(
+                "inicio ******** 2 - upload_bytes start: {} ({} bytes)",
+                object_key,
+                data.len()
+            );
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(object_key)
+                .body(data.into())
+                .content_type(content_type)
+                .send()
+                .await?;
+
+            log::info!("fin ********2 - upload_bytes end: {}", object_key);
+            Ok(self.public_url(object_key))
+        })
+    }
+
+    fn put_file<'a>(
+        &'a self,
+        object_key: &'a str,
+        path: &'a Path,
+        content_type: &'a str,
+    ) -> StoreFuture<'a, String> {
+        Box::pin(async move {
+            // Streams the file from disk with a known content length
+            let body = ByteStream::from_path(path).await?;
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(object_key)
+                .body(body)
+                .content_type(content_type)
+                .send()
+                .await?;
+
+            log::info!("✅ Uploaded file to {}", object_key);
+            Ok(self.public_url(object_key))
+        })
+    }
+
+    /// Download object from S3
+    fn get_object<'a>(&'a self, object_key: &'a str) -> StoreFuture<'a, Vec<u8>> {
+        Box::pin(async move {
+            log::info!("inicio ******** 2 - download_object start: {}", object_key);
+            let response = self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(object_key)
+                .send()
+                .await?;
+
+            let body = response.body.collect().await?;
+            let bytes = body.into_bytes();
+            log::info!(
+                "fin ********2 - download_object end: {} ({} bytes)",
+                object_key,
+                bytes.len()
+            );
+            Ok(bytes.to_vec())
+        })
+    }
+
+    /// Delete object from S3
+    fn delete_object<'a>(&'a self, object_key: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            self.client
+                .delete_object()
+                .bucket(&self.bucket)
+                .key(object_key)
+                .send()
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    fn head_object<'a>(&'a self, object_key: &'a str) -> StoreFuture<'a, Option<ObjectMetadata>> {
+        Box::pin(async move {
+            match self
+                .client
+                .head_object()
+                .bucket(&self.bucket)
+                .key(object_key)
+                .send()
+                .await
+            {
+                Ok(output) => Ok(Some(ObjectMetadata {
+                    size_bytes: output.content_length().unwrap_or(0),
+                    content_type: output.content_type().map(String::from),
+                })),
+                Err(e) if e.as_service_error().is_some_and(|se| se.is_not_found()) => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    /// Generate presigned PUT URL for upload
+    fn presigned_put_url<'a>(
+        &'a self,
+        object_key: &'a str,
+        content_type: &'a str,
+        expires_in_seconds: u64,
+    ) -> StoreFuture<'a, String> {
+        Box::pin(async move {
+            log::info!(
+                "inicio ******** 2 - generate_presigned_put_url start: {}",
+                object_key
+            );
+            let presigning_config =
+                PresigningConfig::expires_in(Duration::from_secs(expires_in_seconds))?;
+
+            let presigned_request = self
+                .client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(object_key)
+                .content_type(content_type)
+                .presigned(presigning_config)
+                .await?;
+
+            Ok(presigned_request.uri().to_string())
+        })
+    }
+
+    /// Generate presigned GET URL for download
+    fn presigned_get_url<'a>(
+        &'a self,
+        object_key: &'a str,
+        expires_in_seconds: u64,
+    ) -> StoreFuture<'a, String> {
+        Box::pin(async move {
+            log::info!(
+                "inicio ******** 2b - generate_presigned_get_url start: {}",
+                object_key
+            );
+            let presigning_config =
+                PresigningConfig::expires_in(Duration::from_secs(expires_in_seconds))?;
+
+            let presigned_request = self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(object_key)
+                .presigned(presigning_config)
+                .await?;
+
+            log::info!(
+                "fin ********2b - generate_presigned_get_url end: {}",
+                object_key
+            );
+            Ok(presigned_request.uri().to_string())
+        })
+    }
+
+    /// List objects under a prefix, following continuation tokens
+    fn list_objects<'a>(&'a self, prefix: &'a str) -> StoreFuture<'a, Vec<StoredObject>> {
+        Box::pin(async move {
+            let mut objects = Vec::new();
+            let mut continuation_token: Option<String> = None;
+
+            loop {
+                let output = self
+                    .client
+                    .list_objects_v2()
+                    .bucket(&self.bucket)
+                    .prefix(prefix)
+                    .set_continuation_token(continuation_token.take())
+                    .send()
+                    .await?;
+
+                objects.extend(output.contents().iter().filter_map(|object| {
+                    Some(StoredObject {
+                        key: object.key()?.to_string(),
+                        size_bytes: object.size().unwrap_or(0),
+                        last_modified: object
+                            .last_modified()
+                            .and_then(|t| chrono::DateTime::from_timestamp(t.secs(), 0)),
+                    })
+                }));
+
+                match output.next_continuation_token() {
+                    Some(token) if output.is_truncated().unwrap_or(false) => {
+                        continuation_token = Some(token.to_string())
+                    }
+                    _ => break,
+                }
+            }
+
+            Ok(objects)
+        })
+    }
+
     /// Get public URL for object
-    pub fn get_public_url(&self, object_key: &str) -> String {
-        let url = format!("https://{}.s3.amazonaws.com/{}", self.bucket, object_key);
+    fn public_url(&self, object_key: &str) -> String {
+        let url = object_url(
+            self.endpoint.as_deref(),
+            &self.region,
+            self.force_path_style,
+            self.public_base_url.as_deref(),
+            &self.bucket,
+            object_key,
+        );
         log::info!(
             "inicio ******** 2c - get_public_url: {} -> {}",
             object_key,
@@ -183,15 +784,120 @@ impl S3Service {
         url
     }
 
-    /// Generate unique object key
-    pub fn generate_object_key(filename: &str) -> String {
-        let uuid = uuid::Uuid::new_v4();
-        let timestamp = chrono::Utc::now().timestamp();
-        let extension = std::path::Path::new(filename)
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("jpg");
+    /// Extract the object key from a URL pointing into our bucket (inverse of
+    /// `public_url`). Also accepts the other S3 addressing styles and the legacy
+    /// global `{bucket}.s3.amazonaws.com` URLs stored by older versions.
+    /// Returns `None` for URLs that belong to any other host or bucket.
+    fn object_key_from_url(&self, url: &str) -> Option<String> {
+        let url = url.split(['?', '#']).next().unwrap_or(url);
+
+        let mut prefixes = vec![
+            format!("{}/", self.public_base()),
+            format!("{}/", self.bucket_url()),
+            format!("https://{}.s3.amazonaws.com/", self.bucket),
+            format!("https://{}.s3.{}.amazonaws.com/", self.bucket, self.region),
+            format!("https://s3.{}.amazonaws.com/{}/", self.region, self.bucket),
+        ];
+        if let Some(endpoint) = &self.endpoint {
+            prefixes.push(format!("{}/{}/", endpoint, self.bucket));
+        }
+
+        prefixes
+            .iter()
+            .find_map(|prefix| url.strip_prefix(prefix.as_str()))
+            .filter(|key| !key.is_empty())
+            .map(String::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_keys_are_timestamp_then_uuid() {
+        assert!(is_legacy_object_key(
+            "captures/1731750000/0b9c8f2e-6a4d-4f8e-9a51-3c2d1e0f9a7b.jpg"
+        ));
+        assert!(!is_legacy_object_key(
+            "captures/0b9c8f2e-6a4d-4f8e-9a51-3c2d1e0f9a7b/1731750000/photo.jpg"
+        ));
+        assert!(!is_legacy_object_key("captures/anonymous/1731750000.jpg"));
+        assert!(!is_legacy_object_key("captures/1731750000/photo.jpg"));
+        assert!(!is_legacy_object_key(
+            "captures/1731750000/x/0b9c8f2e-6a4d-4f8e-9a51-3c2d1e0f9a7b.jpg"
+        ));
+    }
+
+    const KEY: &str = "captures/user/2024/photo.jpg";
+
+    #[test]
+    fn default_aws_uses_virtual_hosted_urls() {
+        assert_eq!(
+            object_url(None, "us-east-1", false, None, "crazydex", KEY),
+            "https://crazydex.s3.us-east-1.amazonaws.com/captures/user/2024/photo.jpg"
+        );
+    }
 
-        format!("captures/{}/{}.{}", timestamp, uuid, extension)
+    #[test]
+    fn aws_region_is_part_of_the_host() {
+        assert_eq!(
+            object_url(None, "eu-west-3", false, None, "crazydex", KEY),
+            "https://crazydex.s3.eu-west-3.amazonaws.com/captures/user/2024/photo.jpg"
+        );
+        assert_eq!(
+            object_url(None, "eu-west-3", true, None, "crazydex", KEY),
+            "https://s3.eu-west-3.amazonaws.com/crazydex/captures/user/2024/photo.jpg"
+        );
+    }
+
+    #[test]
+    fn custom_endpoint_with_path_style() {
+        assert_eq!(
+            object_url(
+                Some("http://localhost:9000/"),
+                "us-east-1",
+                true,
+                None,
+                "crazydex",
+                KEY
+            ),
+            "http://localhost:9000/crazydex/captures/user/2024/photo.jpg"
+        );
+    }
+
+    #[test]
+    fn custom_endpoint_with_virtual_hosted_style() {
+        assert_eq!(
+            object_url(
+                Some("https://storage.example.com"),
+                "us-east-1",
+                false,
+                None,
+                "crazydex",
+                KEY
+            ),
+            "https://crazydex.storage.example.com/captures/user/2024/photo.jpg"
+        );
+    }
+
+    #[test]
+    fn public_base_url_overrides_the_bucket_origin() {
+        assert_eq!(
+            object_url(
+                Some("http://localhost:9000"),
+                "us-east-1",
+                true,
+                Some("https://cdn.example.com/"),
+                "crazydex",
+                KEY
+            ),
+            "https://cdn.example.com/captures/user/2024/photo.jpg"
+        );
+        // Presigned POST still targets the bucket, not the CDN
+        assert_eq!(
+            bucket_url(Some("http://localhost:9000"), "us-east-1", true, "crazydex"),
+            "http://localhost:9000/crazydex"
+        );
     }
 }
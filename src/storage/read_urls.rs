@@ -0,0 +1,128 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::ObjectStore;
+use crate::config::StorageConfig;
+use crate::models::Capture;
+
+/// Cached entries are dropped once the cache grows past this size
+const MAX_CACHED_URLS: usize = 10_000;
+
+/// Builds the read URLs returned by the API. With `SIGNED_READ_URLS` every image,
+/// thumbnail and rendition gets a presigned GET URL; signatures are cached and reused
+/// while at least half of their TTL remains, so repeated reads return stable URLs.
+pub struct ReadUrlSigner {
+    object_store: Arc<dyn ObjectStore>,
+    signed: bool,
+    ttl_seconds: u64,
+    cdn_for_public: bool,
+    cache: Mutex<HashMap<String, (String, DateTime<Utc>)>>,
+}
+
+impl ReadUrlSigner {
+    pub fn new(object_store: Arc<dyn ObjectStore>, config: &StorageConfig) -> Self {
+        Self {
+            object_store,
+            signed: config.signed_read_urls,
+            ttl_seconds: config.read_url_ttl_seconds,
+            cdn_for_public: config.public_captures_use_cdn,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Presigned GET URL for `object_key` and when it expires
+    async fn signed_url(&self, object_key: &str) -> (String, Option<DateTime<Utc>>) {
+        let now = Utc::now();
+        let min_remaining = Duration::seconds(self.ttl_seconds as i64 / 2);
+
+        if let Ok(cache) = self.cache.lock() {
+            if let Some((url, expires_at)) = cache.get(object_key) {
+                if *expires_at - now > min_remaining {
+                    return (url.clone(), Some(*expires_at));
+                }
+            }
+        }
+
+        match self
+            .object_store
+            .presigned_get_url(object_key, self.ttl_seconds)
+            .await
+        {
+            Ok(url) => {
+                let expires_at = now + Duration::seconds(self.ttl_seconds as i64);
+                if let Ok(mut cache) = self.cache.lock() {
+                    if cache.len() >= MAX_CACHED_URLS {
+                        cache.retain(|_, (_, expires)| *expires - now > min_remaining);
+                    }
+                    if cache.len() >= MAX_CACHED_URLS {
+                        cache.clear();
+                    }
+                    cache.insert(object_key.to_string(), (url.clone(), expires_at));
+                }
+                (url, Some(expires_at))
+            }
+            Err(e) => {
+                log::warn!("Failed to presign read URL for {}: {}", object_key, e);
+                (self.object_store.public_url(object_key), None)
+            }
+        }
+    }
+
+    /// Read URL for one object of `capture`
+    async fn read_url(
+        &self,
+        capture_is_public: bool,
+        object_key: &str,
+    ) -> (String, Option<DateTime<Utc>>) {
+        if !self.signed || (capture_is_public && self.cdn_for_public) {
+            (self.object_store.public_url(object_key), None)
+        } else {
+            self.signed_url(object_key).await
+        }
+    }
+
+    /// Read URL for a single object of a capture with the given visibility
+    pub async fn object_url(&self, capture_is_public: bool, object_key: &str) -> String {
+        self.read_url(capture_is_public, object_key).await.0
+    }
+
+    /// Build the image, thumbnail and rendition URLs of a capture from their keys
+    /// with the current storage configuration. Sets `urls_expire_at` to the earliest
+    /// expiry when any of them is signed.
+    pub async fn resolve_capture(&self, capture: &mut Capture) {
+        let mut earliest: Option<DateTime<Utc>> = None;
+        let mut track = |expires_at: Option<DateTime<Utc>>| {
+            if let Some(expires_at) = expires_at {
+                earliest = Some(earliest.map_or(expires_at, |e| e.min(expires_at)));
+            }
+        };
+
+        if let Some(key) = &capture.image_key {
+            let (url, expires_at) = self.read_url(capture.is_public, key).await;
+            capture.image_url = url;
+            track(expires_at);
+        }
+        if let Some(key) = &capture.thumbnail_key {
+            let (url, expires_at) = self.read_url(capture.is_public, key).await;
+            capture.thumbnail_url = Some(url);
+            track(expires_at);
+        }
+        for rendition in &mut capture.renditions {
+            let (url, expires_at) = self
+                .read_url(capture.is_public, &rendition.object_key)
+                .await;
+            rendition.url = url;
+            track(expires_at);
+        }
+
+        capture.urls_expire_at = earliest;
+    }
+
+    /// `resolve_capture` for every capture of a list
+    pub async fn resolve_captures(&self, captures: &mut [Capture]) {
+        for capture in captures.iter_mut() {
+            self.resolve_capture(capture).await;
+        }
+    }
+}
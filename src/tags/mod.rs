@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use crate::config::TagsConfig;
+
+/// Words ending in -s that must not be singularised: invariant plurals, numbers
+/// and place names that only exist in plural form
+const INVARIANT_WORDS: &[&str] = &[
+    "lunes",
+    "martes",
+    "miercoles",
+    "jueves",
+    "viernes",
+    "paraguas",
+    "caries",
+    "atlas",
+    "tres",
+    "andes",
+    "alpes",
+    "galapagos",
+    "canarias",
+    "baleares",
+    "azores",
+    "bahamas",
+    "maldivas",
+    "filipinas",
+];
+
+/// Normalises tags to the format the analysis prompt asks for: lowercase, no
+/// accents, singular and in Spanish. Translations and synonyms come from a
+/// configurable map (`TAG_SYNONYMS`, `TAG_SYNONYMS_FILE`), e.g. `volcano=volcan`.
+pub struct TagNormalizer {
+    synonyms: HashMap<String, String>,
+}
+
+impl TagNormalizer {
+    pub fn new(config: &TagsConfig) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut normalizer = Self {
+            synonyms: HashMap::new(),
+        };
+
+        let mut sources = vec![config.synonyms.clone()];
+        if let Some(path) = &config.synonyms_file {
+            sources.push(
+                std::fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read TAG_SYNONYMS_FILE '{}': {}", path, e))?,
+            );
+        }
+
+        for source in sources {
+            for entry in source.split([',', '\n']) {
+                let entry = entry.split('#').next().unwrap_or_default().trim();
+                if entry.is_empty() {
+                    continue;
+                }
+                let Some((from, to)) = entry.split_once('=') else {
+                    return Err(format!("Invalid tag synonym '{}', expected from=to", entry).into());
+                };
+                let to = normalizer.canonical_form(to);
+                if to.is_empty() {
+                    return Err(format!("Invalid tag synonym '{}', empty target", entry).into());
+                }
+                // Both the plain and the singular spelling of the source map to the target
+                let from = fold(from);
+                normalizer
+                    .synonyms
+                    .insert(singularize_phrase(&from), to.clone());
+                normalizer.synonyms.insert(from, to);
+            }
+        }
+
+        log::info!(
+            "Tag normalizer loaded with {} synonyms",
+            normalizer.synonyms.len()
+        );
+        Ok(normalizer)
+    }
+
+    /// Normalised form of a tag, or `None` if nothing is left of it
+    pub fn normalize(&self, tag: &str) -> Option<String> {
+        let folded = fold(tag);
+        if folded.is_empty() {
+            return None;
+        }
+        if let Some(target) = self.synonyms.get(&folded) {
+            return Some(target.clone());
+        }
+
+        let singular = singularize_phrase(&folded);
+        Some(self.synonyms.get(&singular).cloned().unwrap_or(singular))
+    }
+
+    /// Normalise a list of tags, dropping empties and duplicates and keeping order
+    pub fn normalize_all<'a>(&self, tags: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let mut normalized: Vec<String> = Vec::new();
+        for tag in tags {
+            if let Some(tag) = self.normalize(tag) {
+                if !normalized.contains(&tag) {
+                    normalized.push(tag);
+                }
+            }
+        }
+        normalized
+    }
+
+    /// Form used for prefix lookups (autocomplete): folded but not singularised,
+    /// since the user may still be typing the word
+    pub fn normalize_prefix(&self, prefix: &str) -> String {
+        fold(prefix)
+    }
+
+    fn canonical_form(&self, tag: &str) -> String {
+        singularize_phrase(&fold(tag))
+    }
+}
+
+/// Lowercase, strip accents (keeping ñ as n), and collapse separators to single spaces
+fn fold(tag: &str) -> String {
+    let mut folded = String::with_capacity(tag.len());
+    for c in tag.trim().chars().flat_map(char::to_lowercase) {
+        let c = match c {
+            'á' | 'à' | 'ä' | 'â' | 'ã' | 'å' => 'a',
+            'é' | 'è' | 'ë' | 'ê' => 'e',
+            'í' | 'ì' | 'ï' | 'î' => 'i',
+            'ó' | 'ò' | 'ö' | 'ô' | 'õ' => 'o',
+            'ú' | 'ù' | 'ü' | 'û' => 'u',
+            'ñ' => 'n',
+            'ç' => 'c',
+            '_' | '-' => ' ',
+            c => c,
+        };
+        if c.is_whitespace() {
+            if !folded.is_empty() && !folded.ends_with(' ') {
+                folded.push(' ');
+            }
+        } else if c.is_alphanumeric() {
+            folded.push(c);
+        }
+    }
+    folded.trim_end().to_string()
+}
+
+fn singularize_phrase(phrase: &str) -> String {
+    phrase
+        .split(' ')
+        .map(singularize)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Simple Spanish singularisation of an accentless lowercase word:
+/// luces → luz, volcanes → volcan, ciudades → ciudad, paises → pais, calles → calle,
+/// cascadas → cascada
+fn singularize(word: &str) -> String {
+    if word.chars().count() < 4
+        || !word.ends_with('s')
+        || word.ends_with("is")
+        || word.ends_with("us")
+        || word.ends_with("ss")
+        || INVARIANT_WORDS.contains(&word)
+    {
+        return word.to_string();
+    }
+
+    // luces, peces, cruces (but dulces → dulce)
+    if let Some(stem) = word.strip_suffix("ces") {
+        if stem.ends_with(is_vowel) {
+            return format!("{}z", stem);
+        }
+    }
+
+    // paises, meses, ingleses (the singular ends in a stressed -is/-es)
+    if word.ends_with("ises") || word.ends_with("eses") {
+        return word[..word.len() - 2].to_string();
+    }
+
+    // volcanes, arboles, colores, ciudades, reyes, relojes
+    // (but calles, torres, madres keep the e)
+    if let Some(stem) = word.strip_suffix("es") {
+        let mut last = stem.chars().rev();
+        if let (Some(consonant), Some(before)) = (last.next(), last.next()) {
+            if matches!(consonant, 'n' | 'l' | 'r' | 'd' | 'y' | 'j') && is_vowel(before) {
+                return stem.to_string();
+            }
+        }
+    }
+
+    match word.strip_suffix('s') {
+        Some(stem) if stem.ends_with(is_vowel) => stem.to_string(),
+        _ => word.to_string(),
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalizer(synonyms: &str, synonyms_file: Option<String>) -> TagNormalizer {
+        TagNormalizer::new(&TagsConfig {
+            synonyms: synonyms.to_string(),
+            synonyms_file,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn collapses_model_variants_of_the_same_tag() {
+        let normalizer = normalizer("volcano=volcán, waterfall=cascada", None);
+        let tags = [
+            "Volcán",
+            "volcanes",
+            "volcano",
+            "Cascadas",
+            "waterfall",
+            "paisaje",
+            "Montañas",
+        ];
+
+        assert_eq!(
+            normalizer.normalize_all(tags),
+            vec!["volcan", "cascada", "paisaje", "montana"]
+        );
+    }
+
+    #[test]
+    fn folds_accents_case_and_separators() {
+        let normalizer = normalizer("", None);
+        let tags = [
+            "ÁRBOLES",
+            "Café_Colonial",
+            "  niño  ",
+            "arte-rupestre",
+            "¡Perú!",
+            "",
+        ];
+
+        assert_eq!(
+            normalizer.normalize_all(tags),
+            vec!["arbol", "cafe colonial", "nino", "arte rupestre", "peru"]
+        );
+    }
+
+    #[test]
+    fn loads_synonyms_from_file() {
+        let path = std::env::temp_dir().join(format!("tag-synonyms-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "# English leaks from the model\nbeach=playa\nmountains = montañas # plural target\n",
+        )
+        .unwrap();
+        let normalizer = normalizer("waterfall=cascada", Some(path.display().to_string()));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            normalizer.normalize_all(["Beach", "playas", "mountains", "waterfall", "Montaña"]),
+            vec!["playa", "montana", "cascada"]
+        );
+    }
+
+    #[test]
+    fn keeps_invariant_words_and_stressed_singulars() {
+        let normalizer = normalizer("", None);
+        let tags = [
+            "lunes",
+            "paises",
+            "Andes",
+            "Galápagos",
+            "tres",
+            "crisis",
+            "meses",
+            "luces",
+            "ciudades",
+            "calles",
+        ];
+
+        assert_eq!(
+            normalizer.normalize_all(tags),
+            vec![
+                "lunes",
+                "pais",
+                "andes",
+                "galapagos",
+                "tres",
+                "crisis",
+                "mes",
+                "luz",
+                "ciudad",
+                "calle"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_synonyms() {
+        let config = |synonyms: &str| TagsConfig {
+            synonyms: synonyms.to_string(),
+            synonyms_file: None,
+        };
+
+        assert!(TagNormalizer::new(&config("volcano")).is_err());
+        assert!(TagNormalizer::new(&config("volcano= ")).is_err());
+    }
+
+    #[test]
+    fn prefix_is_folded_but_not_singularised() {
+        assert_eq!(
+            normalizer("", None).normalize_prefix("Volcanes"),
+            "volcanes"
+        );
+    }
+}
@@ -8,6 +8,8 @@ pub struct CapturePublishedEvent {
     pub author_name: Option<String>,
     pub image_url: String,
     pub thumbnail_url: Option<String>,
+    pub blurhash: Option<String>,
+    pub dominant_colors: Option<Vec<String>>,
     pub category: Option<String>,
     pub tags: Option<Vec<String>>,
     pub location: Option<Location>,
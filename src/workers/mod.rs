@@ -1,32 +1,45 @@
-use image::imageops::FilterType;
-use image::ImageFormat;
-use std::io::Cursor;
 use std::sync::Arc;
 use tokio::time::{interval, Duration};
 
 use crate::ai::AIService;
+use crate::config::WorkerConfig;
 use crate::database::DatabaseService;
-use crate::storage::S3Service;
+use crate::imaging;
+use crate::models::{Capture, CaptureRendition, Job, JobType};
+use crate::storage::{self, ObjectStore, S3Service};
+use crate::tags::TagNormalizer;
+
+/// Attempts before a job is marked as failed
+const MAX_JOB_ATTEMPTS: i32 = 3;
+
+/// Original image of the capture whose jobs are being processed, shared by its
+/// thumbnail, EXIF and hash jobs so each capture is downloaded once per tick
+type ImageCache = Option<(uuid::Uuid, Arc<Vec<u8>>)>;
 
 pub struct AnalysisWorker {
     db_service: Arc<DatabaseService>,
-    s3_service: Arc<S3Service>,
+    object_store: Arc<dyn ObjectStore>,
     ai_service: Arc<AIService>,
+    tag_normalizer: Arc<TagNormalizer>,
     interval_seconds: u64,
+    config: WorkerConfig,
 }
 
 impl AnalysisWorker {
     pub fn new(
         db_service: Arc<DatabaseService>,
-        s3_service: Arc<S3Service>,
+        object_store: Arc<dyn ObjectStore>,
         ai_service: Arc<AIService>,
-        interval_seconds: u64,
+        tag_normalizer: Arc<TagNormalizer>,
+        config: WorkerConfig,
     ) -> Self {
         Self {
             db_service,
-            s3_service,
+            object_store,
             ai_service,
-            interval_seconds,
+            tag_normalizer,
+            interval_seconds: config.analysis_interval_seconds,
+            config,
         }
     }
 
@@ -42,67 +55,158 @@ impl AnalysisWorker {
             interval.tick().await;
 
             log::info!("inicio ******** 4 - worker tick");
-            if let Err(e) = self.process_pending_analyses().await {
-                log::error!("Error processing analyses: {}", e);
+            if self.config.image_backfill_enabled {
+                match self
+                    .db_service
+                    .enqueue_image_backfill(self.config.image_backfill_batch_size)
+                    .await
+                {
+                    Ok(0) => {}
+                    Ok(queued) => log::info!("Queued {} image backfill jobs", queued),
+                    Err(e) => log::error!("Error queueing image backfill jobs: {}", e),
+                }
+            }
+            if let Err(e) = self.process_pending_jobs().await {
+                log::error!("Error processing jobs: {}", e);
             }
             log::info!("fin ********4 - worker tick end");
         }
     }
 
-    async fn process_pending_analyses(
-        &self,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let pending_captures = self.db_service.get_pending_analysis(10).await?;
+    async fn process_pending_jobs(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut jobs = self
+            .db_service
+            .claim_pending_jobs(10, MAX_JOB_ATTEMPTS)
+            .await?;
 
-        if pending_captures.is_empty() {
+        if jobs.is_empty() {
             return Ok(());
         }
 
         log::info!(
-            "inicio ******** 5 - Processing {} pending analyses",
-            pending_captures.len()
+            "inicio ******** 5 - Processing {} pending jobs",
+            jobs.len()
         );
 
-        for capture_id in pending_captures {
-            if let Err(e) = self.analyze_capture(&capture_id).await {
-                let error_msg = e.to_string();
-                let is_transient = error_msg.contains("503")
-                    || error_msg.contains("overloaded")
-                    || error_msg.contains("UNAVAILABLE");
-
-                if is_transient {
-                    log::warn!(
-                        "Transient error for capture {}, will retry later: {}",
-                        capture_id,
-                        error_msg
-                    );
-                } else {
-                    log::error!(
-                        "Permanent failure for capture {}: {}",
-                        capture_id,
-                        error_msg
-                    );
-                    // Increment attempts for permanent failures
-                    if let Err(db_err) = self
-                        .db_service
-                        .increment_analysis_attempts(&capture_id)
-                        .await
-                    {
-                        log::error!(
-                            "Failed to increment attempts for {}: {}",
-                            capture_id,
-                            db_err
-                        );
+        // Keep the image jobs of a capture together so they share one download
+        jobs.sort_by_key(|job| job.capture_id);
+        let mut image_cache: ImageCache = None;
+
+        for job in jobs {
+            let capture_id = &job.capture_id;
+            let result = match job.job_type {
+                JobType::Analyze => self.analyze_capture(capture_id).await,
+                JobType::Thumbnail => self.generate_thumbnails(capture_id, &mut image_cache).await,
+                JobType::Exif => self.extract_image_metadata(capture_id, &mut image_cache).await,
+                JobType::Hash => self.compute_content_hash(capture_id, &mut image_cache).await,
+            };
+
+            match result {
+                Ok(()) => {
+                    if let Err(e) = self.db_service.mark_job_completed(&job.id).await {
+                        log::error!("Failed to mark job {} completed: {}", job.id, e);
                     }
                 }
-                continue;
+                Err(e) => self.handle_job_failure(&job, &e.to_string()).await,
             }
         }
 
-        log::info!("fin ********5 - Processing pending analyses end");
+        log::info!("fin ********5 - Processing pending jobs end");
         Ok(())
     }
 
+    /// Reschedule a failed job. Transient AI errors don't consume an attempt;
+    /// everything else backs off exponentially until MAX_JOB_ATTEMPTS.
+    async fn handle_job_failure(&self, job: &Job, error_msg: &str) {
+        let is_transient = error_msg.contains("503")
+            || error_msg.contains("overloaded")
+            || error_msg.contains("UNAVAILABLE");
+
+        let delay_seconds = if is_transient {
+            log::warn!(
+                "Transient error for {} job on capture {}, will retry later: {}",
+                job.job_type.as_str(),
+                job.capture_id,
+                error_msg
+            );
+            60
+        } else {
+            log::error!(
+                "{} job failed for capture {} (attempt {}/{}): {}",
+                job.job_type.as_str(),
+                job.capture_id,
+                job.attempts + 1,
+                MAX_JOB_ATTEMPTS,
+                error_msg
+            );
+            30 * 2i64.pow(job.attempts.clamp(0, 6) as u32) // 30s, 60s, 120s...
+        };
+
+        if let Err(db_err) = self
+            .db_service
+            .retry_job(
+                &job.id,
+                error_msg,
+                delay_seconds,
+                !is_transient,
+                MAX_JOB_ATTEMPTS,
+            )
+            .await
+        {
+            log::error!("Failed to reschedule job {}: {}", job.id, db_err);
+        }
+    }
+
+    /// Load a capture and download its original image, reusing the cached download
+    /// when the previous job was for the same capture.
+    /// Returns `None` when the capture no longer exists (the job is then considered done).
+    async fn load_capture_image(
+        &self,
+        capture_id: &uuid::Uuid,
+        cache: &mut ImageCache,
+    ) -> Result<Option<Arc<Vec<u8>>>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some((cached_id, image_bytes)) = cache {
+            if cached_id == capture_id {
+                return Ok(Some(image_bytes.clone()));
+            }
+        }
+        *cache = None;
+
+        let capture = match self.db_service.get_capture_by_id(capture_id).await? {
+            Some(c) => c,
+            None => {
+                log::warn!("Capture {} not found", capture_id);
+                return Ok(None);
+            }
+        };
+
+        let image_bytes = Arc::new(self.download_image(&capture).await?);
+        *cache = Some((*capture_id, image_bytes.clone()));
+        Ok(Some(image_bytes))
+    }
+
+    /// Download the original image of a capture from storage
+    async fn download_image(
+        &self,
+        capture: &Capture,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let object_key = capture
+            .image_key
+            .clone()
+            .or_else(|| self.object_store.object_key_from_url(&capture.image_url))
+            .ok_or_else(|| format!("Image URL is not in our storage: {}", capture.image_url))?;
+
+        log::info!("inicio ******** 7 - download image start: {}", object_key);
+        let image_bytes = self.object_store.get_object(&object_key).await?;
+        log::info!(
+            "fin ********7 - download image end: {} ({} bytes)",
+            object_key,
+            image_bytes.len()
+        );
+
+        Ok(image_bytes)
+    }
+
     async fn analyze_capture(
         &self,
         capture_id: &uuid::Uuid,
@@ -118,32 +222,18 @@ impl AnalysisWorker {
 
         // Skip if already analyzed (treat empty JSON object as not analyzed)
         match &capture.vision_result {
-            Some(v) => {
-                if v.is_object() {
-                    if let serde_json::Value::Object(map) = v {
-                        if map.is_empty() {
-                            log::info!(
-                                "Capture {} has empty vision_result; will analyze",
-                                capture_id
-                            );
-                        } else {
-                            log::info!(
-                                "Capture {} already has vision_result, marking completed",
-                                capture_id
-                            );
-                            self.db_service.mark_analysis_completed(capture_id).await?;
-                            return Ok(());
-                        }
-                    }
-                } else {
-                    // vision_result exists and is not an object (rare), consider it analyzed
-                    log::info!(
-                        "Capture {} has non-object vision_result, marking completed",
-                        capture_id
-                    );
-                    self.db_service.mark_analysis_completed(capture_id).await?;
-                    return Ok(());
-                }
+            Some(serde_json::Value::Object(map)) if map.is_empty() => {
+                log::info!(
+                    "Capture {} has empty vision_result; will analyze",
+                    capture_id
+                );
+            }
+            Some(_) => {
+                log::info!(
+                    "Capture {} already has vision_result, marking completed",
+                    capture_id
+                );
+                return Ok(());
             }
             None => {
                 // proceed to analyze
@@ -153,35 +243,7 @@ impl AnalysisWorker {
         log::info!("inicio ******** 6 - analyze_capture start: {}", capture_id);
         log::info!("Analyzing capture {}", capture_id);
 
-        // Extract object key from image_url
-        let object_key = match self.extract_object_key(&capture.image_url) {
-            Ok(k) => k,
-            Err(e) => {
-                log::error!(
-                    "Failed to extract object key for capture {}: {}",
-                    capture_id,
-                    e
-                );
-                return Ok(());
-            }
-        };
-
-        // Download image from S3
-        log::info!("inicio ******** 7 - download image start: {}", object_key);
-        let image_bytes = match self.s3_service.download_object(&object_key).await {
-            Ok(b) => {
-                log::info!(
-                    "fin ********7 - download image end: {} ({} bytes)",
-                    object_key,
-                    b.len()
-                );
-                b
-            }
-            Err(e) => {
-                log::error!("Failed to download image for capture {}: {}", capture_id, e);
-                return Ok(());
-            }
-        };
+        let image_bytes = self.download_image(&capture).await?;
 
         // Analyze with AI (with retry logic for transient errors)
         log::info!("inicio ******** 8 - ai analyze start: {}", capture_id);
@@ -272,10 +334,8 @@ impl AnalysisWorker {
             .get("tags")
             .and_then(|v| v.as_array())
             .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str().map(|s| s.to_lowercase().trim().to_string()))
-                    .filter(|s| !s.is_empty())
-                    .collect()
+                self.tag_normalizer
+                    .normalize_all(arr.iter().filter_map(|v| v.as_str()))
             })
             .unwrap_or_default();
 
@@ -299,7 +359,7 @@ impl AnalysisWorker {
             Some(tags.clone())
         };
 
-        if let Err(e) = self
+        let updated = self
             .db_service
             .update_capture_analysis(
                 capture_id,
@@ -310,14 +370,15 @@ impl AnalysisWorker {
                 verified,
                 tags_option.as_ref(),
             )
-            .await
-        {
-            log::error!("Failed to update capture analysis {}: {}", capture_id, e);
-            return Ok(());
-        }
+            .await?;
+
+        // Tags edited by the user were kept; their tables are already in sync
+        let tags_kept = updated
+            .as_ref()
+            .is_some_and(|capture| capture.user_edited_fields.iter().any(|f| f == "tags"));
 
         // Save tags to normalized tables
-        if !tags.is_empty() {
+        if !tags.is_empty() && !tags_kept {
             log::info!("Saving {} tags for capture {}", tags.len(), capture_id);
             if let Err(e) = self.db_service.save_capture_tags(capture_id, &tags).await {
                 log::error!("Failed to save tags for capture {}: {}", capture_id, e);
@@ -327,12 +388,6 @@ impl AnalysisWorker {
             }
         }
 
-        // Mark as completed
-        if let Err(e) = self.db_service.mark_analysis_completed(capture_id).await {
-            log::error!("Failed to mark analysis completed {}: {}", capture_id, e);
-            return Ok(());
-        }
-
         log::info!(
             "fin ********9 - update capture analysis end: {}",
             capture_id
@@ -340,83 +395,526 @@ impl AnalysisWorker {
         log::info!("Capture {} analyzed successfully: category={}, confidence={}, difficulty={}, tags_count={}", 
                    capture_id, category, confidence, difficulty, tags.len());
 
-        // Generate and upload thumbnail (non-blocking - errors won't fail the analysis)
+        Ok(())
+    }
+
+    /// Thumbnail job: renditions and placeholders are generated regardless of the AI analysis status
+    async fn generate_thumbnails(
+        &self,
+        capture_id: &uuid::Uuid,
+        image_cache: &mut ImageCache,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let image_bytes = match self.load_capture_image(capture_id, image_cache).await? {
+            Some(bytes) => bytes,
+            None => return Ok(()),
+        };
+
+        let specs = if self.config.thumbnail_enabled {
+            self.config.thumbnail_renditions.clone()
+        } else {
+            log::info!("Thumbnail generation disabled, computing placeholders only");
+            Vec::new()
+        };
+        let formats = self.config.thumbnail_formats.clone();
+
         log::info!(
-            "inicio ******** 10 - generate thumbnail start: {}",
+            "inicio ******** 10 - generate renditions start: {}",
             capture_id
         );
-        if let Err(e) = self
-            .generate_and_upload_thumbnail(capture_id, &image_bytes)
-            .await
-        {
-            log::error!(
-                "Failed to generate thumbnail for capture {}: {}",
+
+        // Decoding and Lanczos resizing are CPU bound; keep them off the async runtime
+        let (encoded, placeholder) = tokio::task::spawn_blocking(move || {
+            let img = imaging::decode_oriented(&image_bytes)?;
+            let renditions = imaging::render_all(&img, &specs, &formats)?;
+            Ok::<_, image::ImageError>((renditions, imaging::placeholder(&img)))
+        })
+        .await??;
+
+        self.db_service
+            .update_capture_placeholder(
                 capture_id,
-                e
-            );
-            // Continue - thumbnail is optional
-        } else {
-            log::info!("fin ******** 10 - generate thumbnail end: {}", capture_id);
+                &placeholder.blurhash,
+                &placeholder.dominant_colors,
+            )
+            .await?;
+
+        if !encoded.is_empty() {
+            self.upload_renditions(capture_id, encoded).await?;
         }
+        log::info!("fin ******** 10 - generate renditions end: {}", capture_id);
 
         Ok(())
     }
 
-    /// Generate a 200x200 thumbnail and upload to S3
-    async fn generate_and_upload_thumbnail(
+    /// Exif job: store oriented dimensions and the EXIF orientation tag
+    async fn extract_image_metadata(
         &self,
         capture_id: &uuid::Uuid,
-        image_bytes: &[u8],
+        image_cache: &mut ImageCache,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Load image
-        let img = image::load_from_memory(image_bytes)?;
-
-        // Resize to 200x200 maintaining aspect ratio (cover mode)
-        let thumbnail = img.resize_to_fill(200, 200, FilterType::Lanczos3);
-
-        // Encode to JPEG
-        let mut buffer = Cursor::new(Vec::new());
-        thumbnail.write_to(&mut buffer, ImageFormat::Jpeg)?;
-        let thumbnail_bytes = buffer.into_inner();
-
-        // Upload to S3 with thumbnails/ prefix
-        let thumbnail_key = format!("thumbnails/{}.jpg", capture_id);
-        let thumbnail_url = self
-            .s3_service
-            .upload_bytes(&thumbnail_key, thumbnail_bytes, "image/jpeg")
-            .await?;
+        let image_bytes = match self.load_capture_image(capture_id, image_cache).await? {
+            Some(bytes) => bytes,
+            None => return Ok(()),
+        };
 
-        log::info!("Thumbnail uploaded successfully: {}", thumbnail_url);
-
-        // Update capture with thumbnail URL
-        let client = self.db_service.get_client().await?;
-        client
-            .execute(
-                "
-            UPDATE captures SET thumbnail_url = $2, updated_at = NOW()
-            WHERE id = $1
-        ",
-                &[capture_id, &thumbnail_url],
+        let metadata = imaging::read_metadata(&image_bytes)?;
+        self.db_service
+            .update_capture_image_metadata(
+                capture_id,
+                metadata.width as i32,
+                metadata.height as i32,
+                metadata.exif_orientation.map(i16::from),
             )
             .await?;
 
-        log::info!("Thumbnail URL saved to database for capture {}", capture_id);
+        log::info!(
+            "Image metadata saved for capture {}: {}x{}, orientation={:?}",
+            capture_id,
+            metadata.width,
+            metadata.height,
+            metadata.exif_orientation
+        );
 
         Ok(())
     }
 
-    fn extract_object_key(
+    /// Hash job: SHA-256 of the original bytes, used to spot duplicate uploads
+    async fn compute_content_hash(
         &self,
-        url: &str,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        // Extract object key from S3 URL
-        // Example: https://bucket.s3.amazonaws.com/captures/123/uuid.jpg -> captures/123/uuid.jpg
-        let parts: Vec<&str> = url.split('/').collect();
-        if parts.len() >= 4 {
-            let key = parts[3..].join("/");
-            Ok(key)
-        } else {
-            Err("Invalid S3 URL format".into())
+        capture_id: &uuid::Uuid,
+        image_cache: &mut ImageCache,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let image_bytes = match self.load_capture_image(capture_id, image_cache).await? {
+            Some(bytes) => bytes,
+            None => return Ok(()),
+        };
+
+        let hash = imaging::content_hash(&image_bytes);
+        self.db_service
+            .update_capture_content_hash(capture_id, &hash, image_bytes.len() as i64)
+            .await?;
+
+        log::info!("Content hash saved for capture {}: {}", capture_id, hash);
+
+        Ok(())
+    }
+
+    /// Upload encoded renditions to S3 and record them on the capture
+    async fn upload_renditions(
+        &self,
+        capture_id: &uuid::Uuid,
+        encoded: Vec<imaging::EncodedRendition>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut renditions = Vec::with_capacity(encoded.len());
+        for rendition in encoded {
+            // thumbnails/{capture_id}/{name}.{ext}
+            let object_key = format!(
+                "thumbnails/{}/{}.{}",
+                capture_id,
+                rendition.name,
+                rendition.format.extension()
+            );
+            let size_bytes = rendition.bytes.len() as i64;
+            let url = self
+                .object_store
+                .put_object(&object_key, rendition.bytes, rendition.format.content_type())
+                .await?;
+
+            renditions.push(CaptureRendition {
+                name: rendition.name,
+                format: rendition.format.as_str().to_string(),
+                width: rendition.width as i32,
+                height: rendition.height as i32,
+                object_key,
+                url,
+                size_bytes,
+            });
+        }
+
+        // The first size in the first format keeps backing the legacy thumbnail_url
+        self.db_service
+            .save_capture_renditions(capture_id, &renditions, renditions.first())
+            .await?;
+
+        log::info!(
+            "Saved {} renditions for capture {}",
+            renditions.len(),
+            capture_id
+        );
+
+        Ok(())
+    }
+}
+
+/// Aborts multipart uploads that were started but never completed or aborted,
+/// so their parts stop accruing storage costs.
+pub struct MultipartCleanupWorker {
+    s3_service: Arc<S3Service>,
+    interval_seconds: u64,
+    ttl_hours: i64,
+}
+
+impl MultipartCleanupWorker {
+    pub fn new(s3_service: Arc<S3Service>, interval_seconds: u64, ttl_hours: i64) -> Self {
+        Self {
+            s3_service,
+            interval_seconds,
+            ttl_hours,
+        }
+    }
+
+    pub async fn start(self) {
+        log::info!(
+            "Starting multipart cleanup worker with interval: {}s (ttl {}h)",
+            self.interval_seconds,
+            self.ttl_hours
+        );
+
+        let mut interval = interval(Duration::from_secs(self.interval_seconds));
+
+        loop {
+            interval.tick().await;
+
+            match self.abort_stale_uploads().await {
+                Ok(0) => {}
+                Ok(count) => log::info!("🧹 Aborted {} stale multipart uploads", count),
+                Err(e) => log::error!("Error cleaning up multipart uploads: {}", e),
+            }
+        }
+    }
+
+    async fn abort_stale_uploads(&self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let cutoff = chrono::Utc::now() - chrono::Duration::hours(self.ttl_hours);
+        let uploads = self.s3_service.list_multipart_uploads("captures/").await?;
+
+        let mut aborted = 0;
+        for upload in uploads {
+            // Uploads without an initiation date are left for the bucket lifecycle rule
+            if upload.initiated_at.is_none_or(|t| t >= cutoff) {
+                continue;
+            }
+
+            match self
+                .s3_service
+                .abort_multipart_upload(&upload.object_key, &upload.upload_id)
+                .await
+            {
+                Ok(()) => aborted += 1,
+                Err(e) => log::warn!(
+                    "Failed to abort stale multipart upload {}: {}",
+                    upload.object_key,
+                    e
+                ),
+            }
+        }
+
+        Ok(aborted)
+    }
+}
+
+/// Result of one garbage collection pass
+#[derive(Debug, Default)]
+pub struct GcReport {
+    pub scanned: usize,
+    pub orphaned: usize,
+    pub deleted: usize,
+    pub failed: usize,
+    pub reclaimed_bytes: i64,
+}
+
+/// Deletes objects under `captures/` and `thumbnails/` that no capture or rendition
+/// references: presigned uploads that never became captures, thumbnails of deleted
+/// captures, and leftovers of failed deletes. Objects younger than the grace period
+/// are kept, since an upload may still be on its way to becoming a capture.
+pub struct OrphanCollector {
+    db_service: Arc<DatabaseService>,
+    object_store: Arc<dyn ObjectStore>,
+    grace_period_hours: i64,
+}
+
+impl OrphanCollector {
+    const PREFIXES: [&'static str; 2] = ["captures/", "thumbnails/"];
+
+    pub fn new(
+        db_service: Arc<DatabaseService>,
+        object_store: Arc<dyn ObjectStore>,
+        grace_period_hours: i64,
+    ) -> Self {
+        Self {
+            db_service,
+            object_store,
+            grace_period_hours,
+        }
+    }
+
+    /// Run the collector every `interval_seconds`
+    pub async fn start(self, interval_seconds: u64, dry_run: bool) {
+        log::info!(
+            "Starting orphan GC with interval: {}s (grace {}h, dry run: {})",
+            interval_seconds,
+            self.grace_period_hours,
+            dry_run
+        );
+
+        let mut interval = interval(Duration::from_secs(interval_seconds));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.run(dry_run).await {
+                log::error!("Error running orphan GC: {}", e);
+            }
+        }
+    }
+
+    /// One collection pass. With `dry_run` orphans are only reported.
+    pub async fn run(
+        &self,
+        dry_run: bool,
+    ) -> Result<GcReport, Box<dyn std::error::Error + Send + Sync>> {
+        log::info!("inicio ******** 11 - orphan GC start (dry run: {})", dry_run);
+
+        // Load references before listing: an object uploaded and referenced in
+        // between is still protected by the grace period
+        let (mut referenced, legacy_urls) =
+            self.db_service.get_referenced_object_keys().await?;
+        referenced.extend(
+            legacy_urls
+                .iter()
+                .filter_map(|url| self.object_store.object_key_from_url(url)),
+        );
+
+        let cutoff = chrono::Utc::now() - chrono::Duration::hours(self.grace_period_hours);
+        let mut report = GcReport::default();
+
+        for prefix in Self::PREFIXES {
+            for object in self.object_store.list_objects(prefix).await? {
+                report.scanned += 1;
+
+                // Unknown age counts as recent
+                let is_old = object.last_modified.is_some_and(|t| t < cutoff);
+                if !is_old || referenced.contains(&object.key) {
+                    continue;
+                }
+
+                report.orphaned += 1;
+                if dry_run {
+                    log::info!(
+                        "🧹 [dry run] Orphan {} ({} bytes)",
+                        object.key,
+                        object.size_bytes
+                    );
+                    report.reclaimed_bytes += object.size_bytes;
+                    continue;
+                }
+
+                match self.object_store.delete_object(&object.key).await {
+                    Ok(()) => {
+                        log::info!(
+                            "🧹 Deleted orphan {} ({} bytes)",
+                            object.key,
+                            object.size_bytes
+                        );
+                        report.deleted += 1;
+                        report.reclaimed_bytes += object.size_bytes;
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to delete orphan {}: {}", object.key, e);
+                        report.failed += 1;
+                    }
+                }
+            }
+        }
+
+        log::info!(
+            "fin ********11 - orphan GC end: scanned={} orphaned={} deleted={} failed={} reclaimed_bytes={}{}",
+            report.scanned,
+            report.orphaned,
+            report.deleted,
+            report.failed,
+            report.reclaimed_bytes,
+            if dry_run { " (dry run)" } else { "" }
+        );
+
+        Ok(report)
+    }
+}
+
+/// Deletes the storage objects queued in the `storage_deletions` outbox when a
+/// capture is deleted. Failures back off exponentially (capped at one hour) and
+/// are marked `failed` after MAX_STORAGE_DELETION_ATTEMPTS.
+pub struct StorageDeletionWorker {
+    db_service: Arc<DatabaseService>,
+    object_store: Arc<dyn ObjectStore>,
+    interval_seconds: u64,
+}
+
+impl StorageDeletionWorker {
+    const MAX_STORAGE_DELETION_ATTEMPTS: i32 = 10;
+    const BATCH_SIZE: i32 = 50;
+
+    pub fn new(
+        db_service: Arc<DatabaseService>,
+        object_store: Arc<dyn ObjectStore>,
+        interval_seconds: u64,
+    ) -> Self {
+        Self {
+            db_service,
+            object_store,
+            interval_seconds,
+        }
+    }
+
+    pub async fn start(self) {
+        log::info!(
+            "Starting storage deletion worker with interval: {}s",
+            self.interval_seconds
+        );
+
+        let mut interval = interval(Duration::from_secs(self.interval_seconds));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.process_pending_deletions().await {
+                log::error!("Error processing storage deletions: {}", e);
+            }
+        }
+    }
+
+    async fn process_pending_deletions(
+        &self,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let deletions = self
+            .db_service
+            .claim_storage_deletions(Self::BATCH_SIZE)
+            .await?;
+
+        if deletions.is_empty() {
+            return Ok(());
         }
+
+        log::info!(
+            "inicio ******** 12 - Processing {} storage deletions",
+            deletions.len()
+        );
+
+        for deletion in deletions {
+            match self.object_store.delete_object(&deletion.object_key).await {
+                Ok(()) => {
+                    log::info!("🗑️ Deleted object {}", deletion.object_key);
+                    if let Err(e) = self
+                        .db_service
+                        .complete_storage_deletion(&deletion.id)
+                        .await
+                    {
+                        log::error!(
+                            "Failed to mark storage deletion {} completed: {}",
+                            deletion.id,
+                            e
+                        );
+                    }
+                }
+                Err(e) => {
+                    let delay_seconds = (30i64 << deletion.attempts.clamp(0, 7)).min(3600);
+                    log::warn!(
+                        "❌ Failed to delete object {} (attempt {}), retrying in {}s: {}",
+                        deletion.object_key,
+                        deletion.attempts + 1,
+                        delay_seconds,
+                        e
+                    );
+                    if let Err(e) = self
+                        .db_service
+                        .retry_storage_deletion(
+                            &deletion.id,
+                            &e.to_string(),
+                            delay_seconds,
+                            Self::MAX_STORAGE_DELETION_ATTEMPTS,
+                        )
+                        .await
+                    {
+                        log::error!(
+                            "Failed to reschedule storage deletion {}: {}",
+                            deletion.id,
+                            e
+                        );
+                    }
+                }
+            }
+        }
+
+        log::info!("fin ********12 - Processing storage deletions end");
+        Ok(())
+    }
+}
+
+/// Permanently deletes captures that have been in the trash longer than the
+/// retention period. Their storage objects go through the deletion outbox.
+pub struct TrashPurgeWorker {
+    db_service: Arc<DatabaseService>,
+    object_store: Arc<dyn ObjectStore>,
+    retention_days: i32,
+}
+
+impl TrashPurgeWorker {
+    const BATCH_SIZE: i32 = 100;
+
+    pub fn new(
+        db_service: Arc<DatabaseService>,
+        object_store: Arc<dyn ObjectStore>,
+        retention_days: i32,
+    ) -> Self {
+        Self {
+            db_service,
+            object_store,
+            retention_days,
+        }
+    }
+
+    pub async fn start(self, interval_seconds: u64) {
+        log::info!(
+            "Starting trash purge worker with interval: {}s (retention {} days)",
+            interval_seconds,
+            self.retention_days
+        );
+
+        let mut interval = interval(Duration::from_secs(interval_seconds));
+
+        loop {
+            interval.tick().await;
+
+            match self.purge_expired().await {
+                Ok(0) => {}
+                Ok(count) => log::info!("🗑️ Purged {} captures from the trash", count),
+                Err(e) => log::error!("Error purging trash: {}", e),
+            }
+        }
+    }
+
+    async fn purge_expired(&self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let captures = self
+            .db_service
+            .get_expired_trash(self.retention_days, Self::BATCH_SIZE)
+            .await?;
+
+        let mut purged = 0;
+        for capture in captures {
+            let (image_key, thumbnail_key) =
+                storage::capture_object_keys(self.object_store.as_ref(), &capture);
+            let object_keys: Vec<String> = image_key.into_iter().chain(thumbnail_key).collect();
+
+            match self
+                .db_service
+                .purge_trashed_capture(&capture.id, &object_keys, self.retention_days)
+                .await
+            {
+                Ok(true) => purged += 1,
+                Ok(false) => {}
+                Err(e) => log::warn!("Failed to purge capture {}: {}", capture.id, e),
+            }
+        }
+
+        Ok(purged)
     }
 }